@@ -0,0 +1,157 @@
+use std::time::{Duration, Instant};
+
+use crate::hash_bucket::HashTable;
+
+/// Shape of the keys a benchmark run works on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyKind {
+    /// `0`, `1`, `2`, ...
+    Sequential,
+    /// Pseudo-random hex keys from a fixed seed, the same on every run
+    Random,
+    /// Keys which all share one djb2 hash, so every insert collides.
+    /// Keys are at most 16 bytes, which leaves room for 3^8 of them
+    Adversarial,
+}
+
+// 8 blocks of 2 bytes fill the 16 byte key, 3 blocks to pick from
+const MAX_ADVERSARIAL_KEYS: usize = 3_usize.pow(8);
+
+/// Time taken by each phase of a benchmark run
+#[derive(Debug, Clone, Copy)]
+pub struct BenchResult {
+    pub insert: Duration,
+    pub get: Duration,
+    pub del: Duration,
+}
+
+/// Insert, read back and delete `n` keys of `key_kind` on the
+/// sharded table, every key is stored with itself as the value
+pub fn run_bench(n: usize, key_kind: KeyKind) -> BenchResult {
+    assert!(
+        key_kind != KeyKind::Adversarial || n <= MAX_ADVERSARIAL_KEYS,
+        "Only {MAX_ADVERSARIAL_KEYS} adversarial keys fit the key size"
+    );
+
+    let keys = generate_keys(n, key_kind);
+    let mut hash = HashTable::new();
+
+    // ----------------------------
+    // benchmark insert operation
+    // ----------------------------
+    let start = Instant::now();
+
+    for key in &keys {
+        hash.set(key, key)
+            .unwrap_or_else(|err| panic!("Unable to insert {key}: {err}"));
+    }
+
+    let insert = start.elapsed();
+
+    // ----------------------------
+    // benchmark get operation
+    // ----------------------------
+    let start = Instant::now();
+
+    for key in &keys {
+        assert_eq!(hash.get(key), Some(key.to_owned()));
+    }
+
+    let get = start.elapsed();
+
+    // ----------------------------
+    // benchmark del operation
+    // ----------------------------
+    let start = Instant::now();
+
+    for key in &keys {
+        assert_eq!(hash.del(key), Some(key.to_owned()));
+    }
+
+    let del = start.elapsed();
+
+    BenchResult { insert, get, del }
+}
+
+fn generate_keys(n: usize, key_kind: KeyKind) -> Vec<String> {
+    match key_kind {
+        KeyKind::Sequential => (0..n).map(|i| i.to_string()).collect(),
+        KeyKind::Random => {
+            // xorshift64, a fixed seed keeps runs comparable
+            let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+
+            (0..n)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+
+                    format!("{state:x}")
+                })
+                .collect()
+        }
+        KeyKind::Adversarial => {
+            // 33 * c1 + c2 is the same for each of these pairs, so
+            // any keys made of 8 of them hash the same under djb2
+            const BLOCKS: [&str; 3] = ["Bc", "CB", "D!"];
+
+            (0..n)
+                .map(|i| {
+                    let mut i = i;
+
+                    (0..8)
+                        .map(|_| {
+                            let block = BLOCKS[i % 3];
+                            i /= 3;
+
+                            block
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash_bucket::Hashable;
+
+    #[test]
+    fn test_run_bench() {
+        run_bench(1000, KeyKind::Sequential);
+        run_bench(1000, KeyKind::Random);
+        run_bench(200, KeyKind::Adversarial);
+    }
+
+    #[test]
+    fn test_adversarial_keys_collide() {
+        let keys = generate_keys(50, KeyKind::Adversarial);
+        let hash = keys[0].hash();
+
+        assert!(keys.iter().all(|key| key.hash() == hash));
+
+        // still distinct keys
+        let mut unique = keys.clone();
+        unique.sort();
+        unique.dedup();
+
+        assert_eq!(unique.len(), keys.len());
+    }
+
+    #[test]
+    fn test_adversarial_keys_are_distinct() {
+        let mut keys = generate_keys(MAX_ADVERSARIAL_KEYS, KeyKind::Adversarial);
+        keys.sort();
+        keys.dedup();
+
+        assert_eq!(keys.len(), MAX_ADVERSARIAL_KEYS);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only 6561 adversarial keys fit the key size")]
+    fn test_run_bench_too_many_adversarial_keys() {
+        run_bench(MAX_ADVERSARIAL_KEYS + 1, KeyKind::Adversarial);
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+
+use crate::hash::{HashTable, SetError};
+
+/// `hash::HashTable` holding at most `capacity` keys, setting a new
+/// key when it's full evicts the least recently used one first
+pub struct LruHashTable {
+    table: HashTable,
+    // keys from least to most recently used
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl LruHashTable {
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "Capacity should be at least 1 key");
+
+        Self {
+            table: HashTable::new(),
+            order: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// Insert or update a key and mark it as the most recently used,
+    /// nothing is evicted if the key or value doesn't fit
+    pub fn set(&mut self, key: &str, value: &str) -> Result<Option<String>, SetError> {
+        self.table.check_fits(key, value.as_bytes())?;
+
+        if !self.touch(key) && self.table.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                // `pop` never compacts, the table keeps its size
+                self.table.pop(&oldest);
+            }
+        }
+
+        let old = self.table.set(key, value)?;
+
+        if old.is_none() {
+            self.order.push_back(key.to_string());
+        }
+
+        Ok(old)
+    }
+
+    /// Value of the key, which becomes the most recently used
+    pub fn get(&mut self, key: &str) -> Option<String> {
+        let value = self.table.get(key)?;
+        self.touch(key);
+
+        Some(value)
+    }
+
+    pub fn del(&mut self, key: &str) -> Option<String> {
+        let value = self.table.pop(key)?;
+
+        if let Some(position) = self.order.iter().position(|k| k == key) {
+            self.order.remove(position);
+        }
+
+        Some(value)
+    }
+
+    // move the key to the back of the order, `false` if it isn't stored
+    fn touch(&mut self, key: &str) -> bool {
+        match self.order.iter().position(|k| k == key) {
+            Some(position) => {
+                let key = self.order.remove(position).unwrap();
+                self.order.push_back(key);
+
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = LruHashTable::new(3);
+
+        cache.set("a", "1").unwrap();
+        cache.set("b", "2").unwrap();
+        cache.set("c", "3").unwrap();
+
+        // full, "a" is the oldest
+        cache.set("d", "4").unwrap();
+
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get("a"), None);
+        assert_eq!(cache.get("d"), Some(String::from("4")));
+
+        // reading "b" makes "c" the oldest
+        assert_eq!(cache.get("b"), Some(String::from("2")));
+        cache.set("e", "5").unwrap();
+
+        assert_eq!(cache.get("c"), None);
+        assert_eq!(cache.get("b"), Some(String::from("2")));
+
+        // updating a stored key never evicts
+        assert_eq!(cache.set("e", "50").unwrap(), Some(String::from("5")));
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get("d"), Some(String::from("4")));
+    }
+
+    #[test]
+    fn test_oversized_set_keeps_entries() {
+        let mut cache = LruHashTable::new(1);
+
+        cache.set("a", "1").unwrap();
+
+        assert!(cache.set(&"k".repeat(100), "value").is_err());
+        assert_eq!(cache.get("a"), Some(String::from("1")));
+
+        assert_eq!(cache.del("a"), Some(String::from("1")));
+        assert!(cache.is_empty());
+    }
+}
@@ -1,336 +1,2320 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::hash::BuildHasher;
+use std::io;
+use std::path::Path;
+
 trait Hashable {
-    fn hash(&self) -> usize;
+    fn hash_with_seed(&self, seed: u64) -> usize;
 }
 
 impl Hashable for &str {
-    // using the djb2 algo (https://theartincode.stanis.me/008-djb2/)
-    fn hash(&self) -> usize {
-        let mut result: usize = 5381;
+    // using the djb2 algo (https://theartincode.stanis.me/008-djb2/), the
+    // seed is folded in at every step so colliding keys can't be crafted
+    // without knowing it; a seed of 0 is plain djb2
+    fn hash_with_seed(&self, seed: u64) -> usize {
+        let seed = seed as usize;
+        let mut result: usize = 5381 ^ seed;
 
         for c in self.chars() {
-            result = ((result << 5).wrapping_add(result)).wrapping_add(c as usize);
+            result = ((result << 5).wrapping_add(result)).wrapping_add(c as usize) ^ seed;
         }
 
         result
     }
 }
 
-struct HashItem {
-    key: [u8; 32],
-    value: [u8; 96],
+// no of buckets a table starts with, it's never compacted below this
+const INITIAL_SIZE: usize = 32;
+
+// default layout, a bucket is 130 (32 + 2 + 96) bytes
+const KEY_SIZE: usize = 32;
+const VALUE_SIZE: usize = 96;
+
+// the value length is stored as a u16 between the key and the value
+const LEN_SIZE: usize = 2;
+
+// saved tables start with size, no_of_taken, key_size, value_size, the
+// hash seed, the probe strategy, the probe budget and the case mode as
+// little endian u64s, followed by the raw buckets and then the
+// overflowed pairs laid out as buckets too
+const HEADER_SIZE: usize = 8 * 8;
+
+// A deleted bucket is marked by this byte in the first key position. It can
+// never start a valid UTF-8 key, so it doesn't clash with stored keys, and
+// it's distinct from `\0` which marks a bucket that was never used.
+const TOMBSTONE: u8 = 0xFF;
+
+struct HashItem<'a> {
+    key: &'a [u8],
+    value: &'a [u8],
 }
 
-impl HashItem {
-    fn new(key: &str, value: &str) -> [u8; 128] {
-        let mut buffer = [b'\0'; 128];
+impl<'a> HashItem<'a> {
+    fn to_bytes(key: &str, value: &[u8], key_size: usize, value_size: usize) -> Vec<u8> {
+        let mut buffer = vec![b'\0'; key_size + LEN_SIZE + value_size];
 
         let mut key_bytes = Vec::from(key.as_bytes());
-        let mut value_bytes = Vec::from(value.as_bytes());
+        let mut value_bytes = Vec::from(value);
+
+        key_bytes.resize(key_size, b'\0');
+        value_bytes.resize(value_size, b'\0');
+
+        let value_len = (value.len() as u16).to_le_bytes();
 
-        key_bytes.resize(32, b'\0');
-        value_bytes.resize(96, b'\0');
+        buffer[0..key_size].copy_from_slice(&key_bytes);
+        buffer[key_size..(key_size + LEN_SIZE)].copy_from_slice(&value_len);
+        buffer[(key_size + LEN_SIZE)..].copy_from_slice(&value_bytes);
 
-        buffer[0..32].copy_from_slice(&key_bytes);
-        buffer[32..128].copy_from_slice(&value_bytes);
+        buffer
+    }
+
+    fn tombstone(bucket_size: usize) -> Vec<u8> {
+        let mut buffer = vec![b'\0'; bucket_size];
+        buffer[0] = TOMBSTONE;
 
         buffer
     }
 
-    fn from_bytes(bytes: &[u8; 128]) -> Option<Self> {
-        if bytes[0] == b'\0' {
+    fn from_bytes(bytes: &'a [u8], key_size: usize) -> Option<Self> {
+        if bytes[0] == b'\0' || bytes[0] == TOMBSTONE {
             None
         } else {
+            let len_bytes = bytes[key_size..(key_size + LEN_SIZE)].try_into().unwrap();
+            let value_bytes = &bytes[(key_size + LEN_SIZE)..];
+
+            // never trust the stored length past the value region
+            let value_len =
+                std::cmp::min(u16::from_le_bytes(len_bytes) as usize, value_bytes.len());
+
             Some(Self {
-                key: bytes[0..32].try_into().unwrap(),
-                value: bytes[32..128].try_into().unwrap(),
+                key: &bytes[0..key_size],
+                value: &value_bytes[..value_len],
             })
         }
     }
+
+    fn key_matches(&self, key: &str, case_insensitive: bool) -> bool {
+        let len = self
+            .key
+            .iter()
+            .rposition(|&b| b != b'\0')
+            .map_or(0, |i| i + 1);
+
+        if case_insensitive {
+            String::from_utf8_lossy(&self.key[..len]).to_lowercase() == key.to_lowercase()
+        } else {
+            &self.key[..len] == key.as_bytes()
+        }
+    }
+
+    fn key_string(&self) -> String {
+        String::from_utf8_lossy(self.key)
+            .trim_end_matches('\0')
+            .to_string()
+    }
+
+    fn value_string(&self) -> String {
+        String::from_utf8_lossy(self.value).to_string()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SetError {
+    KeyTooLong {
+        max: usize,
+        got: usize,
+    },
+    ValueTooLong {
+        max: usize,
+        got: usize,
+    },
+    /// Keys are padded with `\0`, one inside the key would make it
+    /// match another key
+    KeyContainsNul,
+}
+
+impl fmt::Display for SetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SetError::KeyTooLong { max, got } => {
+                write!(f, "key is {got} bytes, max allowed is {max}")
+            }
+            SetError::ValueTooLong { max, got } => {
+                write!(f, "value is {got} bytes, max allowed is {max}")
+            }
+            SetError::KeyContainsNul => write!(f, "key contains a NUL byte"),
+        }
+    }
+}
+
+impl std::error::Error for SetError {}
+
+/// Everything `try_set` can fail with, besides the input not fitting
+/// it reports a broken internal state instead of panicking
+#[derive(Debug, PartialEq, Eq)]
+pub enum StoreError {
+    Set(SetError),
+    OutOfBounds { offset: usize, len: usize },
+    Resize(ResizeError),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StoreError::Set(err) => err.fmt(f),
+            StoreError::OutOfBounds { offset, len } => {
+                write!(
+                    f,
+                    "Index out of bounds, bucket at {offset} is past the {len} byte buffer"
+                )
+            }
+            StoreError::Resize(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<SetError> for StoreError {
+    fn from(err: SetError) -> Self {
+        StoreError::Set(err)
+    }
+}
+
+impl From<ResizeError> for StoreError {
+    fn from(err: ResizeError) -> Self {
+        StoreError::Resize(err)
+    }
+}
+
+/// Why `resize_to` refused a size
+#[derive(Debug, PartialEq, Eq)]
+pub enum ResizeError {
+    /// The stored keys would reach the load factor at `size`
+    TooSmall { size: usize, len: usize },
+}
+
+impl fmt::Display for ResizeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResizeError::TooSmall { size, len } => {
+                write!(
+                    f,
+                    "{size} buckets can't hold {len} keys under the load factor"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResizeError {}
+
+/// How the slots past the home slot of a key are walked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProbeStrategy {
+    /// Move to the next slot on every step
+    #[default]
+    Linear,
+    /// Move by a step size taken from a second hash of the key, so
+    /// keys sharing a home slot spread out instead of clustering
+    Double,
+}
+
+/// How far stored keys sit from the slot they hash to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProbeStats {
+    pub average_probe_len: f64,
+    pub max_probe_len: usize,
+    /// Ratio of taken buckets to the size of the table
+    pub occupancy: f64,
 }
 
+#[derive(Clone)]
 pub struct HashTable {
     kvs: Vec<u8>,
     size: usize,
     no_of_taken: usize,
+    key_size: usize,
+    value_size: usize,
+    // keys are matched and hashed lowercased
+    case_insensitive: bool,
+    // folded into every hash, random unless given to `new_seeded`
+    seed: u64,
+    probe_strategy: ProbeStrategy,
+    // probe steps taken before a new key spills into `overflow`
+    max_probe: usize,
+    // keys which found no free slot within `max_probe` steps, in the
+    // order they were added. Not counted in `no_of_taken`
+    overflow: Vec<(String, Vec<u8>)>,
+}
+
+impl Default for HashTable {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl HashTable {
     pub fn new() -> Self {
+        Self::with_layout(KEY_SIZE, VALUE_SIZE)
+    }
+
+    /// Create a table whose buckets hold keys of up to `key_size` bytes
+    /// and values of up to `value_size` bytes
+    pub fn with_layout(key_size: usize, value_size: usize) -> Self {
+        assert!(key_size > 0, "Key size should be at least 1 byte");
+        assert!(
+            value_size <= u16::MAX as usize,
+            "Value size should fit in the u16 length prefix"
+        );
+
+        let size = INITIAL_SIZE;
+
         Self {
-            kvs: vec![b'\0'; 4096],
-            size: 32,
+            kvs: vec![b'\0'; size * (key_size + LEN_SIZE + value_size)],
+            size,
             no_of_taken: 0,
+            key_size,
+            value_size,
+            case_insensitive: false,
+            seed: RandomState::new().hash_one(0),
+            probe_strategy: ProbeStrategy::Linear,
+            max_probe: usize::MAX,
+            overflow: Vec::new(),
         }
     }
 
-    pub fn set(&mut self, key: &str, value: &str) {
-        let load_factor = (self.size as f64 * 0.75) as usize;
+    /// Create a table with a fixed hash seed, tables with the same
+    /// seed place keys in the same slots
+    pub fn new_seeded(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Self::new()
+        }
+    }
 
-        if self.no_of_taken >= load_factor {
-            self.extend();
+    /// Create a table which probes with `probe_strategy` on collisions
+    pub fn with_probe_strategy(probe_strategy: ProbeStrategy) -> Self {
+        Self {
+            probe_strategy,
+            ..Self::new()
         }
+    }
 
-        let mut index = self.get_hash_index(&key);
-        let bucket = HashItem::new(key, value);
+    /// Create a table which stops probing after `max_probe` steps, a
+    /// new key which finds no free slot by then is kept in an overflow
+    /// list instead. It caps the probe length of a clustered table,
+    /// but every overflowed pair is a separate heap allocation on top
+    /// of the bucket buffer, and a lookup which misses the buckets
+    /// scans the whole list
+    pub fn with_max_probe(max_probe: usize) -> Self {
+        assert!(max_probe > 0, "Probe budget should be at least 1 step");
 
-        for _ in 0..self.size {
-            let offset = index * 128;
-            assert!(offset + 128 <= self.kvs.len(), "Index out of bounds");
+        Self {
+            max_probe,
+            ..Self::new()
+        }
+    }
 
-            let bytes = self.kvs[offset..(offset + 128)].try_into().unwrap();
+    /// Create a table where keys differing only in case are the same
+    /// key, the case of the last `set` is kept for display
+    pub fn new_case_insensitive() -> Self {
+        Self {
+            case_insensitive: true,
+            ..Self::new()
+        }
+    }
 
-            match HashItem::from_bytes(bytes) {
-                Some(item) => {
-                    let stored_key = String::from_utf8_lossy(&item.key)
-                        .trim_end_matches('\0')
-                        .to_string();
+    /// Insert or update a key, nothing is written if the key or
+    /// value doesn't fit in the bucket layout. Like `HashMap::insert`
+    /// the replaced value is handed back, `None` on a fresh insert
+    pub fn set(&mut self, key: &str, value: &str) -> Result<Option<String>, SetError> {
+        let old = self.set_bytes(key, value.as_bytes())?;
 
-                    if &stored_key == key {
-                        self.kvs[offset..(offset + 128)].copy_from_slice(&bucket);
-                        break;
-                    }
-                }
-                None => {
-                    self.kvs[offset..(offset + 128)].clone_from_slice(&bucket);
+        Ok(old.map(|bytes| String::from_utf8_lossy(&bytes).to_string()))
+    }
 
-                    self.no_of_taken += 1;
-                    break;
-                }
-            }
+    /// Same as `set` but for raw values, the exact bytes are kept
+    /// so values may contain or end with `\0`
+    pub fn set_bytes(&mut self, key: &str, value: &[u8]) -> Result<Option<Vec<u8>>, SetError> {
+        self.check_fits(key, value)?;
+
+        Ok(self.insert(key, value))
+    }
+
+    /// Same as `set`, but a broken internal state is returned as
+    /// an error instead of panicking
+    pub fn try_set(&mut self, key: &str, value: &str) -> Result<Option<String>, StoreError> {
+        self.check_fits(key, value.as_bytes())?;
+
+        let old = self.try_insert(key, value.as_bytes())?;
 
-            index = (index + 1) % self.size;
+        Ok(old.map(|bytes| String::from_utf8_lossy(&bytes).to_string()))
+    }
+
+    pub(crate) fn check_fits(&self, key: &str, value: &[u8]) -> Result<(), SetError> {
+        if key.contains('\0') {
+            return Err(SetError::KeyContainsNul);
+        }
+
+        if key.len() > self.key_size {
+            return Err(SetError::KeyTooLong {
+                max: self.key_size,
+                got: key.len(),
+            });
         }
+
+        if value.len() > self.value_size {
+            return Err(SetError::ValueTooLong {
+                max: self.value_size,
+                got: value.len(),
+            });
+        }
+
+        Ok(())
     }
 
-    pub fn get(&self, key: &str) -> Option<String> {
+    // key and value are expected to fit the layout, hands back
+    // the bytes of the value which got replaced
+    fn insert(&mut self, key: &str, value: &[u8]) -> Option<Vec<u8>> {
+        self.try_insert(key, value)
+            .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    fn try_insert(&mut self, key: &str, value: &[u8]) -> Result<Option<Vec<u8>>, StoreError> {
+        let load_factor = (self.size as f64 * 0.75) as usize;
+
+        if self.no_of_taken >= load_factor {
+            self.extend()?;
+        }
+
+        let bucket_size = self.bucket_size();
+
         let mut index = self.get_hash_index(key);
+        let step = self.probe_step(key);
+        let bucket = HashItem::to_bytes(key, value, self.key_size, self.value_size);
 
-        for _ in 0..self.size {
-            let offset = index * 128;
-            assert!(offset + 128 <= self.kvs.len(), "Index out of bounds");
+        // first deleted or empty slot seen while probing, the key
+        // could still live further down the chain past a tombstone
+        let mut free_index: Option<usize> = None;
 
-            let bytes = self.kvs[offset..(offset + 128)].try_into().unwrap();
+        for _ in 0..self.probe_len() {
+            let offset = index * bucket_size;
 
-            match HashItem::from_bytes(bytes) {
-                Some(item) => {
-                    let stored_key = String::from_utf8_lossy(&item.key)
-                        .trim_end_matches('\0')
-                        .to_string();
+            if offset + bucket_size > self.kvs.len() {
+                return Err(StoreError::OutOfBounds {
+                    offset,
+                    len: self.kvs.len(),
+                });
+            }
 
-                    if &stored_key == key {
-                        let stored_value = String::from_utf8_lossy(&item.value)
-                            .trim_end_matches('\0')
-                            .to_string();
+            let bytes = &self.kvs[offset..(offset + bucket_size)];
+
+            match HashItem::from_bytes(bytes, self.key_size) {
+                Some(item) => {
+                    if item.key_matches(key, self.case_insensitive) {
+                        let old = item.value.to_vec();
 
-                        return Some(stored_value);
+                        self.kvs[offset..(offset + bucket_size)].copy_from_slice(&bucket);
+                        return Ok(Some(old));
                     }
                 }
+                None if bytes[0] == TOMBSTONE => {
+                    free_index.get_or_insert(index);
+                }
                 None => {
-                    return None;
+                    free_index.get_or_insert(index);
+                    break;
                 }
             }
 
-            index = (index + 1) % self.size;
+            index = (index + step) % self.size;
         }
 
-        None
+        // the key may have spilled over before a slot was freed
+        if let Some(position) = self.overflow_position(key) {
+            let (stored_key, stored_value) = &mut self.overflow[position];
+            *stored_key = key.to_string();
+
+            return Ok(Some(std::mem::replace(stored_value, value.to_vec())));
+        }
+
+        match free_index {
+            Some(index) => {
+                let offset = index * bucket_size;
+
+                self.kvs[offset..(offset + bucket_size)].clone_from_slice(&bucket);
+                self.no_of_taken += 1;
+            }
+            None => self.overflow.push((key.to_string(), value.to_vec())),
+        }
+
+        Ok(None)
     }
 
-    pub fn del(&mut self, key: &str) -> Option<String> {
-        let mut index = self.get_hash_index(&key);
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.get_value_bytes(key)
+            .map(|value| String::from_utf8_lossy(value).to_string())
+    }
 
-        for _ in 0..self.size {
-            let offset = index * 128;
-            assert!(offset + 128 <= self.kvs.len(), "Index out of bounds");
+    /// Edit the value of the key in place, `false` if it isn't stored.
+    /// An edited value past the value size is cut at the last char
+    /// which fits
+    pub fn update<F: FnOnce(&mut String)>(&mut self, key: &str, f: F) -> bool {
+        let Some(index) = self.find_index(key) else {
+            let Some(position) = self.overflow_position(key) else {
+                return false;
+            };
 
-            let bytes = self.kvs[offset..(offset + 128)].try_into().unwrap();
-            let bucket = [b'\0'; 128];
+            let mut value = String::from_utf8_lossy(&self.overflow[position].1).to_string();
 
-            match HashItem::from_bytes(bytes) {
-                Some(item) => {
-                    let stored_key = String::from_utf8_lossy(&item.key)
-                        .trim_end_matches('\0')
-                        .to_string();
+            f(&mut value);
+
+            let len = self.fitting_len(&value);
+            self.overflow[position].1 = value.as_bytes()[..len].to_vec();
 
-                    if &stored_key == key {
-                        // Actually remove the item by zeroing out the bucket
-                        self.kvs[offset..(offset + 128)].copy_from_slice(&bucket);
+            return true;
+        };
 
-                        let stored_value = String::from_utf8_lossy(&item.value)
-                            .trim_end_matches('\0')
-                            .to_string();
+        let Some(item) = HashItem::from_bytes(self.bucket(index), self.key_size) else {
+            return false;
+        };
 
-                        // Decrement no_of_taken only if it's not already 0
-                        if self.no_of_taken > 0 {
-                            self.no_of_taken -= 1;
-                        }
+        let stored_key = item.key_string();
+        let mut value = item.value_string();
 
-                        // Optional: Compact if load is very low
-                        if self.no_of_taken <= (self.size as f64 * 0.1) as usize {
-                            self.compact();
-                        }
+        f(&mut value);
 
-                        return Some(stored_value);
+        let len = self.fitting_len(&value);
+
+        let bucket = HashItem::to_bytes(
+            &stored_key,
+            &value.as_bytes()[..len],
+            self.key_size,
+            self.value_size,
+        );
+        let offset = index * self.bucket_size();
+
+        self.kvs[offset..(offset + bucket.len())].copy_from_slice(&bucket);
+
+        true
+    }
+
+    /// Add `suffix` to the end of the stored value, it's written into
+    /// the bucket right after the stored length so the value is never
+    /// decoded. A missing key is set to `suffix`, nothing is written
+    /// if the longer value doesn't fit the value size
+    pub fn append(&mut self, key: &str, suffix: &str) -> Result<(), StoreError> {
+        let Some(index) = self.find_index(key) else {
+            if let Some(position) = self.overflow_position(key) {
+                let value = &mut self.overflow[position].1;
+
+                if value.len() + suffix.len() > self.value_size {
+                    return Err(SetError::ValueTooLong {
+                        max: self.value_size,
+                        got: value.len() + suffix.len(),
                     }
+                    .into());
                 }
-                None => {
-                    return None;
-                }
+
+                value.extend_from_slice(suffix.as_bytes());
+
+                return Ok(());
             }
 
-            index = (index + 1) % self.size;
+            self.try_set(key, suffix)?;
+
+            return Ok(());
+        };
+
+        let len_offset = index * self.bucket_size() + self.key_size;
+        let value_offset = len_offset + LEN_SIZE;
+
+        let len_bytes = self.kvs[len_offset..value_offset].try_into().unwrap();
+
+        // never trust the stored length past the value region
+        let len = std::cmp::min(u16::from_le_bytes(len_bytes) as usize, self.value_size);
+        let new_len = len + suffix.len();
+
+        if new_len > self.value_size {
+            return Err(SetError::ValueTooLong {
+                max: self.value_size,
+                got: new_len,
+            }
+            .into());
         }
 
-        None
+        self.kvs[(value_offset + len)..(value_offset + new_len)].copy_from_slice(suffix.as_bytes());
+        self.kvs[len_offset..value_offset].copy_from_slice(&(new_len as u16).to_le_bytes());
+
+        Ok(())
     }
 
-    fn extend(&mut self) {
-        let new_size = self.size * 2;
+    /// Value of the key, or `default` if it isn't stored. The found
+    /// value is handed back as is, only `default` gets copied
+    pub fn get_or(&self, key: &str, default: &str) -> String {
+        self.get(key).unwrap_or_else(|| default.to_string())
+    }
 
-        let mut new_self = HashTable {
-            kvs: vec![b'\0'; new_size * 128],
-            size: new_size,
-            no_of_taken: 0,
-        };
+    /// Value of the key, or an empty string if it isn't stored
+    pub fn get_or_default(&self, key: &str) -> String {
+        self.get(key).unwrap_or_default()
+    }
 
-        let mut offset: usize = 0;
+    /// Exact bytes stored for the key
+    pub fn get_bytes(&self, key: &str) -> Option<Vec<u8>> {
+        self.get_value_bytes(key).map(<[u8]>::to_vec)
+    }
 
-        for i in 1..=self.size {
-            let end_offset: usize = i * 128;
-            let bytes: &[u8; 128] = self.kvs[offset..end_offset].try_into().unwrap();
-            let bucket = HashItem::from_bytes(bytes);
+    /// Stored bytes of the value borrowed straight from the buffer,
+    /// cut at the stored length so nothing is copied
+    pub fn get_value_bytes(&self, key: &str) -> Option<&[u8]> {
+        match self.find_index(key) {
+            Some(index) => {
+                HashItem::from_bytes(self.bucket(index), self.key_size).map(|item| item.value)
+            }
+            None => self
+                .overflow_position(key)
+                .map(|position| self.overflow[position].1.as_slice()),
+        }
+    }
 
-            match bucket {
-                Some(item) => {
-                    let key = String::from_utf8_lossy(&item.key)
-                        .trim_end_matches('\0')
-                        .to_string();
+    /// Look up every key in one pass, the values line up with `keys`.
+    /// Each key is padded into one reused buffer and compared against
+    /// the raw key bytes of the buckets, so no stored key is decoded
+    pub fn get_many(&self, keys: &[&str]) -> Vec<Option<String>> {
+        let mut padded = vec![b'\0'; self.key_size];
+
+        keys.iter()
+            .map(|key| {
+                // these can't be compared byte for byte against the buckets
+                let value =
+                    if self.case_insensitive || key.len() > self.key_size || key.contains('\0') {
+                        self.get_value_bytes(key)
+                    } else {
+                        padded.fill(b'\0');
+                        padded[..key.len()].copy_from_slice(key.as_bytes());
+
+                        match self.find_padded_index(key, &padded) {
+                            Some(index) => HashItem::from_bytes(self.bucket(index), self.key_size)
+                                .map(|item| item.value),
+                            None => self
+                                .overflow_position(key)
+                                .map(|position| self.overflow[position].1.as_slice()),
+                        }
+                    };
 
-                    let value = String::from_utf8_lossy(&item.value)
-                        .trim_end_matches('\0')
-                        .to_string();
+                value.map(|value| String::from_utf8_lossy(value).to_string())
+            })
+            .collect()
+    }
 
-                    new_self.set(&key, &value);
-                }
-                None => {}
-            }
+    /// Same as `get_many` with the lookups spread across the rayon
+    /// thread pool, the values still line up with `keys`
+    #[cfg(feature = "rayon")]
+    pub fn par_get_many(&self, keys: &[&str]) -> Vec<Option<String>> {
+        use rayon::prelude::*;
 
-            offset = end_offset;
-        }
+        keys.par_iter().map(|key| self.get(key)).collect()
+    }
 
-        *self = new_self;
+    /// Number of stored keys, overflowed ones included
+    pub fn len(&self) -> usize {
+        self.no_of_taken + self.overflow.len()
     }
 
-    fn compact(&mut self) {
-        let new_size = self.size / 2;
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 
-        let mut new_self = HashTable {
-            kvs: vec![b'\0'; new_size * 128],
-            size: new_size,
-            no_of_taken: 0,
-        };
+    /// Number of slots, taken or not. Unlike `len` it only changes
+    /// when the table is resized
+    pub fn capacity(&self) -> usize {
+        self.size
+    }
 
-        for i in 0..self.size {
-            let offset = i * 128;
-            let bytes: &[u8; 128] = self.kvs[offset..(offset + 128)].try_into().unwrap();
+    /// Bytes allocated for the table, the buffer capacity plus the
+    /// struct itself. Empty buckets count too, it's not the size of
+    /// the stored data
+    pub fn memory_footprint(&self) -> usize {
+        let overflow: usize = self
+            .overflow
+            .iter()
+            .map(|(key, value)| key.capacity() + value.capacity())
+            .sum();
+
+        self.kvs.capacity()
+            + self.overflow.capacity() * std::mem::size_of::<(String, Vec<u8>)>()
+            + overflow
+            + std::mem::size_of::<Self>()
+    }
 
-            match HashItem::from_bytes(bytes) {
-                Some(item) => {
-                    let key = String::from_utf8_lossy(&item.key)
-                        .trim_end_matches('\0')
-                        .to_string();
+    /// Remove every key, the buffer keeps its current size
+    pub fn clear(&mut self) {
+        self.kvs.fill(b'\0');
+        self.no_of_taken = 0;
+        self.overflow.clear();
+    }
+
+    /// Check if the key is stored, without decoding its value
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.find_index(key).is_some() || self.overflow_position(key).is_some()
+    }
 
-                    let value = String::from_utf8_lossy(&item.value)
-                        .trim_end_matches('\0')
-                        .to_string();
+    pub fn del(&mut self, key: &str) -> Option<String> {
+        let stored_value = self.remove(key);
 
-                    new_self.set(&key, &value);
-                }
-                None => {}
-            }
+        self.compact_if_sparse();
+
+        stored_value
+    }
+
+    /// Remove the key and hand back its value in a single probe.
+    /// Unlike `del` the table is never compacted, so draining and
+    /// refilling it like a queue doesn't keep rebuilding the buffer
+    pub fn pop(&mut self, key: &str) -> Option<String> {
+        self.remove(key)
+    }
+
+    /// Move the value of `old` over to `new`, `false` if `old` isn't
+    /// stored, `new` already is or doesn't fit the key size. The old
+    /// slot is left as a tombstone so the probe chain stays intact
+    pub fn rename(&mut self, old: &str, new: &str) -> bool {
+        if self.contains_key(new) {
+            return false;
         }
 
-        *self = new_self;
+        let Some(value) = self.get_bytes(old) else {
+            return false;
+        };
+
+        if self.check_fits(new, &value).is_err() {
+            return false;
+        }
+
+        self.remove(old);
+        self.insert(new, &value);
+
+        true
     }
 
-    fn get_hash_index(&self, key: &str) -> usize {
-        key.hash() % self.size
+    /// Delete every key, the removed values line up with `keys`.
+    /// Deleted buckets become tombstones, so colliding keys left in
+    /// the table stay reachable, and the table is compacted once at
+    /// the end rather than in between
+    pub fn del_many(&mut self, keys: &[&str]) -> Vec<Option<String>> {
+        let stored_values = keys.iter().map(|key| self.remove(key)).collect();
+
+        self.compact_if_sparse();
+
+        stored_values
     }
-}
 
-#[cfg(test)]
-mod tests {
+    /// Iterate over the stored (key, value) pairs in slot order,
+    /// empty and deleted buckets are skipped. Overflowed pairs come
+    /// last, in the order they were added
+    pub fn iter(&self) -> impl Iterator<Item = (String, String)> + '_ {
+        self.items()
+            .map(|item| (item.key_string(), item.value_string()))
+    }
 
-    use super::*;
+    /// Copy the stored pairs into a std `HashMap`
+    pub fn to_hashmap(&self) -> HashMap<String, String> {
+        self.iter().collect()
+    }
 
-    #[test]
-    fn test_set_items() {
-        let mut hash_table = HashTable::new();
+    pub fn keys(&self) -> impl Iterator<Item = String> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
 
-        // insert 400 items into the table
-        for i in 0..400 {
-            let key = i.to_string();
-            hash_table.set(&key, &key);
-        }
+    pub fn values(&self) -> impl Iterator<Item = String> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
 
-        assert_eq!(hash_table.no_of_taken, 400);
+    /// Every stored key starting with `prefix`, in slot order
+    pub fn keys_with_prefix(&self, prefix: &str) -> Vec<String> {
+        self.items()
+            .map(|item| item.key_string())
+            .filter(|key| key.starts_with(prefix))
+            .collect()
     }
 
-    #[test]
-    fn test_get_items() {
-        let mut hash_table = HashTable::new();
+    /// Probe distance of every stored key, 0 for a key sitting
+    /// in its home slot
+    pub fn probe_stats(&self) -> ProbeStats {
+        let distances: Vec<usize> = self
+            .kvs
+            .chunks_exact(self.bucket_size())
+            .enumerate()
+            .filter_map(|(index, bytes)| {
+                let item = HashItem::from_bytes(bytes, self.key_size)?;
+                let key = item.key_string();
+
+                let mut slot = self.get_hash_index(&key);
+                let step = self.probe_step(&key);
+
+                // steps taken along the probe sequence of the key
+                (0..self.size).find(|_| {
+                    let found = slot == index;
+                    slot = (slot + step) % self.size;
+
+                    found
+                })
+            })
+            .collect();
 
-        // insert 400 items into the table
-        for i in 0..400 {
-            let key = i.to_string();
-            hash_table.set(&key, &key);
+        let average_probe_len = if distances.is_empty() {
+            0.0
+        } else {
+            distances.iter().sum::<usize>() as f64 / distances.len() as f64
+        };
+
+        ProbeStats {
+            average_probe_len,
+            max_probe_len: distances.iter().copied().max().unwrap_or(0),
+            occupancy: self.no_of_taken as f64 / self.size as f64,
         }
+    }
 
-        // fetch 400 items into from the table
-        for i in 0..400 {
-            let key = i.to_string();
+    /// Write the table to `path` so it can be restored with `load_from`
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut buffer = Vec::with_capacity(HEADER_SIZE + self.kvs.len());
+
+        let fields = [
+            self.size as u64,
+            self.no_of_taken as u64,
+            self.key_size as u64,
+            self.value_size as u64,
+            self.seed,
+            self.probe_strategy as u64,
+            self.max_probe as u64,
+            self.case_insensitive as u64,
+        ];
+
+        for field in fields {
+            buffer.extend_from_slice(&field.to_le_bytes());
+        }
 
-            if let Some(val) = hash_table.get(&key) {
-                println!("Value for key {key} is {}", val);
+        buffer.extend_from_slice(&self.kvs);
 
-                assert_eq!(val, key);
-            }
+        for (key, value) in &self.overflow {
+            buffer.extend_from_slice(&HashItem::to_bytes(
+                key,
+                value,
+                self.key_size,
+                self.value_size,
+            ));
         }
+
+        fs::write(path, buffer)
     }
 
-    #[test]
-    fn test_del_items() {
-        let mut hash_table = HashTable::new();
+    /// Restore a table written by `save_to`, a truncated or
+    /// mismatched file is reported as `InvalidData`
+    pub fn load_from<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
 
-        // insert 400 items into the table
-        for i in 0..400 {
-            let key = i.to_string();
-            hash_table.set(&key, &key);
+        if bytes.len() < HEADER_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "File is too short to hold the header",
+            ));
         }
 
-        // del 400 items into from the table
-        for i in 0..400 {
-            let key = i.to_string();
+        let mut header = bytes[..HEADER_SIZE]
+            .chunks_exact(8)
+            .map(|field| u64::from_le_bytes(field.try_into().unwrap()));
+
+        let size = header.next().unwrap() as usize;
+        let no_of_taken = header.next().unwrap() as usize;
+        let key_size = header.next().unwrap() as usize;
+        let value_size = header.next().unwrap() as usize;
+        let seed = header.next().unwrap();
+
+        let probe_strategy = match header.next().unwrap() {
+            0 => ProbeStrategy::Linear,
+            1 => ProbeStrategy::Double,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Header holds an unknown probe strategy",
+                ))
+            }
+        };
 
-            if let Some(val) = hash_table.del(&key) {
-                assert_eq!(val, key);
+        // saved as u64, a budget past usize on this target is unlimited
+        let max_probe = usize::try_from(header.next().unwrap()).unwrap_or(usize::MAX);
+
+        let case_insensitive = match header.next().unwrap() {
+            0 => false,
+            1 => true,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Header holds an unknown case mode",
+                ))
             }
+        };
+
+        if size == 0
+            || key_size == 0
+            || value_size > u16::MAX as usize
+            || no_of_taken > size
+            || max_probe == 0
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Header holds an invalid table layout",
+            ));
         }
 
-        let mut count = 0;
+        let bucket_size = key_size + LEN_SIZE + value_size;
+        let data = &bytes[HEADER_SIZE..];
 
-        // get 400 items into from the table
-        for i in 0..400 {
-            let key = i.to_string();
+        let (kvs, overflow) = match bucket_size.checked_mul(size) {
+            Some(len) if len <= data.len() && (data.len() - len) % bucket_size == 0 => {
+                data.split_at(len)
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Bucket data doesn't match the header",
+                ))
+            }
+        };
 
-            let val = hash_table.get(&key);
+        let overflow = overflow
+            .chunks_exact(bucket_size)
+            .map(|bytes| {
+                HashItem::from_bytes(bytes, key_size)
+                    .map(|item| (item.key_string(), item.value.to_vec()))
+                    .ok_or_else(|| {
+                        io::Error::new(io::ErrorKind::InvalidData, "Overflow holds an empty bucket")
+                    })
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            kvs: kvs.to_vec(),
+            size,
+            no_of_taken,
+            key_size,
+            value_size,
+            case_insensitive,
+            seed,
+            probe_strategy,
+            max_probe,
+            overflow,
+        })
+    }
 
-            if val != None {
-                println!("{key}:{:?}", Some(val));
-                count += 1;
-            }
-        }
+    // tombstone the bucket of `key` without compacting
+    fn remove(&mut self, key: &str) -> Option<String> {
+        let Some(index) = self.find_index(key) else {
+            let position = self.overflow_position(key)?;
+            let (_, value) = self.overflow.remove(position);
 
-        assert_eq!(count, 0);
+            return Some(String::from_utf8_lossy(&value).to_string());
+        };
+        let bucket_size = self.bucket_size();
+        let offset = index * bucket_size;
+
+        let stored_value =
+            HashItem::from_bytes(self.bucket(index), self.key_size).map(|item| item.value_string());
+
+        // Leave a tombstone so the probe chain stays intact
+        // for keys stored after this one
+        self.kvs[offset..(offset + bucket_size)].copy_from_slice(&HashItem::tombstone(bucket_size));
+
+        // Decrement no_of_taken only if it's not already 0
+        if self.no_of_taken > 0 {
+            self.no_of_taken -= 1;
+        }
+
+        stored_value
+    }
+
+    fn compact_if_sparse(&mut self) {
+        // Optional: Compact if load is very low
+        if self.len() <= (self.size as f64 * 0.1) as usize {
+            self.compact().unwrap_or_else(|err| panic!("{err}"));
+        }
+    }
+
+    /// Probe for the slot holding `key`, overflowed keys aren't in a slot
+    fn find_index(&self, key: &str) -> Option<usize> {
+        let mut index = self.get_hash_index(key);
+        let step = self.probe_step(key);
+
+        for _ in 0..self.probe_len() {
+            let bytes = self.bucket(index);
+
+            match HashItem::from_bytes(bytes, self.key_size) {
+                Some(item) => {
+                    if item.key_matches(key, self.case_insensitive) {
+                        return Some(index);
+                    }
+                }
+                // deleted slot, keep probing
+                None if bytes[0] == TOMBSTONE => {}
+                None => {
+                    return None;
+                }
+            }
+
+            index = (index + step) % self.size;
+        }
+
+        None
+    }
+
+    /// Same probe as `find_index`, with `key` already padded to the
+    /// key size so a bucket matches on its raw key bytes
+    fn find_padded_index(&self, key: &str, padded: &[u8]) -> Option<usize> {
+        let mut index = self.get_hash_index(key);
+        let step = self.probe_step(key);
+
+        for _ in 0..self.probe_len() {
+            let bytes = self.bucket(index);
+
+            match bytes[0] {
+                b'\0' => return None,
+                // deleted slot, keep probing
+                TOMBSTONE => {}
+                _ if &bytes[..self.key_size] == padded => return Some(index),
+                _ => {}
+            }
+
+            index = (index + step) % self.size;
+        }
+
+        None
+    }
+
+    /// Position of `key` in the overflow list
+    fn overflow_position(&self, key: &str) -> Option<usize> {
+        self.overflow.iter().position(|(stored_key, _)| {
+            if self.case_insensitive {
+                stored_key.to_lowercase() == key.to_lowercase()
+            } else {
+                stored_key == key
+            }
+        })
+    }
+
+    // probe steps a lookup takes before giving up on the buckets
+    fn probe_len(&self) -> usize {
+        std::cmp::min(self.size, self.max_probe)
+    }
+
+    // longest prefix of `value` which fits the value size and ends
+    // on a char boundary
+    fn fitting_len(&self, value: &str) -> usize {
+        let mut len = std::cmp::min(value.len(), self.value_size);
+        while !value.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        len
+    }
+
+    fn extend(&mut self) -> Result<(), StoreError> {
+        let mut new_size = self.size * 2;
+
+        // overflowed keys may not fit in just twice the slots
+        while self.len() >= (new_size as f64 * 0.75) as usize {
+            new_size *= 2;
+        }
+
+        self.try_resize_to(new_size)
+    }
+
+    /// Rehash every stored key into a table of `new_size` buckets,
+    /// which has to keep them under the load factor
+    pub fn resize_to(&mut self, new_size: usize) -> Result<(), ResizeError> {
+        match self.try_resize_to(new_size) {
+            Ok(()) => Ok(()),
+            Err(StoreError::Resize(err)) => Err(err),
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    // same as `resize_to`, a broken internal state is returned
+    // instead of panicking. The table is left as it was on an error
+    fn try_resize_to(&mut self, new_size: usize) -> Result<(), StoreError> {
+        // at the load factor the next insert would extend again,
+        // overflowed keys get another go at a slot so they count too
+        if self.len() >= (new_size as f64 * 0.75) as usize {
+            return Err(StoreError::Resize(ResizeError::TooSmall {
+                size: new_size,
+                len: self.len(),
+            }));
+        }
+
+        let mut new_self = self.empty_with_size(new_size);
+
+        // tombstones are dropped here, they're not carried into the new table
+        for item in self.items() {
+            new_self.try_insert(&item.key_string(), item.value)?;
+        }
+
+        *self = new_self;
+
+        Ok(())
+    }
+
+    /// Rebuild the table at its current size, dropping tombstones
+    /// so collided keys move back towards their home slot
+    pub fn rehash(&mut self) {
+        let mut new_self = self.empty_with_size(self.size);
+
+        for item in self.items() {
+            new_self.insert(&item.key_string(), item.value);
+        }
+
+        *self = new_self;
+    }
+
+    fn compact(&mut self) -> Result<(), StoreError> {
+        let new_size = std::cmp::max(self.size / 2, INITIAL_SIZE);
+
+        if new_size == self.size {
+            return Ok(());
+        }
+
+        // only called on a sparse table, half the size still fits
+        self.try_resize_to(new_size)
+    }
+
+    /// Table whose buffer lost its buckets while `size` still counts
+    /// them, every probe lands past the end of it
+    #[cfg(test)]
+    fn with_truncated_buffer() -> Self {
+        let mut hash_table = Self::new();
+        hash_table.kvs.clear();
+
+        hash_table
+    }
+
+    /// Empty table with the same layout as `self` but `size` buckets
+    fn empty_with_size(&self, size: usize) -> Self {
+        Self {
+            kvs: vec![b'\0'; size * self.bucket_size()],
+            size,
+            no_of_taken: 0,
+            key_size: self.key_size,
+            value_size: self.value_size,
+            case_insensitive: self.case_insensitive,
+            seed: self.seed,
+            probe_strategy: self.probe_strategy,
+            max_probe: self.max_probe,
+            overflow: Vec::new(),
+        }
+    }
+
+    /// Every occupied bucket in slot order, then the overflowed pairs
+    fn items(&self) -> impl Iterator<Item = HashItem<'_>> {
+        let overflow = self.overflow.iter().map(|(key, value)| HashItem {
+            key: key.as_bytes(),
+            value,
+        });
+
+        self.kvs
+            .chunks_exact(self.bucket_size())
+            .filter_map(|bytes| HashItem::from_bytes(bytes, self.key_size))
+            .chain(overflow)
+    }
+
+    fn bucket(&self, index: usize) -> &[u8] {
+        let bucket_size = self.bucket_size();
+        let offset = index * bucket_size;
+        assert!(
+            offset + bucket_size <= self.kvs.len(),
+            "Index out of bounds"
+        );
+
+        &self.kvs[offset..(offset + bucket_size)]
+    }
+
+    fn bucket_size(&self) -> usize {
+        self.key_size + LEN_SIZE + self.value_size
+    }
+
+    fn get_hash_index(&self, key: &str) -> usize {
+        let hash = self.hash_key(key, self.seed);
+
+        // an empty buffer has no slots to probe
+        hash.checked_rem(self.size).unwrap_or(0)
+    }
+
+    // slots moved on every probe step, coprime with the size so
+    // the probe visits every slot before wrapping around
+    fn probe_step(&self, key: &str) -> usize {
+        match self.probe_strategy {
+            ProbeStrategy::Linear => 1,
+            ProbeStrategy::Double if self.size <= 1 => 1,
+            ProbeStrategy::Double => {
+                // another seed than the home slot, keys sharing
+                // a home slot mostly get different steps
+                let mut step = 1 + self.hash_key(key, !self.seed) % (self.size - 1);
+
+                // `size - 1` is always coprime, so this stops before it
+                while gcd(step, self.size) != 1 {
+                    step += 1;
+                }
+
+                step
+            }
+        }
+    }
+
+    fn hash_key(&self, key: &str, seed: u64) -> usize {
+        if self.case_insensitive {
+            key.to_lowercase().as_str().hash_with_seed(seed)
+        } else {
+            key.hash_with_seed(seed)
+        }
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_set_items() {
+        let mut hash_table = HashTable::new();
+
+        // insert 400 items into the table
+        for i in 0..400 {
+            let key = i.to_string();
+            hash_table.set(&key, &key).unwrap();
+        }
+
+        assert_eq!(hash_table.no_of_taken, 400);
+    }
+
+    #[test]
+    fn test_get_items() {
+        let mut hash_table = HashTable::new();
+
+        // insert 400 items into the table
+        for i in 0..400 {
+            let key = i.to_string();
+            hash_table.set(&key, &key).unwrap();
+        }
+
+        // fetch 400 items into from the table
+        for i in 0..400 {
+            let key = i.to_string();
+
+            if let Some(val) = hash_table.get(&key) {
+                println!("Value for key {key} is {}", val);
+
+                assert_eq!(val, key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_del_items() {
+        let mut hash_table = HashTable::new();
+
+        // insert 400 items into the table
+        for i in 0..400 {
+            let key = i.to_string();
+            hash_table.set(&key, &key).unwrap();
+        }
+
+        // del 400 items into from the table
+        for i in 0..400 {
+            let key = i.to_string();
+
+            if let Some(val) = hash_table.del(&key) {
+                assert_eq!(val, key);
+            }
+        }
+
+        let mut count = 0;
+
+        // get 400 items into from the table
+        for i in 0..400 {
+            let key = i.to_string();
+
+            let val = hash_table.get(&key);
+
+            if val.is_some() {
+                println!("{key}:{:?}", Some(val));
+                count += 1;
+            }
+        }
+
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_delete_then_get_colliding_keys() {
+        let mut hash_table = HashTable::new();
+
+        // find two keys which land on the same home slot
+        let first = String::from("0");
+        let home = hash_table.get_hash_index(&first);
+
+        let second = (1..)
+            .map(|i: usize| i.to_string())
+            .find(|key| hash_table.get_hash_index(key) == home)
+            .unwrap();
+
+        hash_table.set(&first, "first").unwrap();
+        hash_table.set(&second, "second").unwrap();
+
+        // keep the table above the compaction threshold
+        for i in 0..10 {
+            let key = format!("filler_{i}");
+            hash_table.set(&key, &key).unwrap();
+        }
+
+        assert_eq!(hash_table.del(&first), Some(String::from("first")));
+
+        assert_eq!(hash_table.get(&first), None);
+        assert_eq!(hash_table.get(&second), Some(String::from("second")));
+
+        // the tombstone is reused for a fresh insert
+        hash_table.set(&first, "again").unwrap();
+        assert_eq!(hash_table.get(&first), Some(String::from("again")));
+        assert_eq!(hash_table.get(&second), Some(String::from("second")));
+    }
+
+    #[test]
+    fn test_with_layout_long_keys() {
+        let mut hash_table = HashTable::with_layout(36, 96);
+
+        let key = "550e8400-e29b-41d4-a716-446655440000";
+        assert_eq!(key.len(), 36);
+
+        hash_table.set(key, "uuid").unwrap();
+        assert_eq!(hash_table.get(key), Some(String::from("uuid")));
+
+        // keys sharing the first 32 bytes stay distinct
+        let other = "550e8400-e29b-41d4-a716-446655441111";
+
+        hash_table.set(other, "other").unwrap();
+        assert_eq!(hash_table.get(key), Some(String::from("uuid")));
+        assert_eq!(hash_table.get(other), Some(String::from("other")));
+    }
+
+    #[test]
+    fn test_with_layout_survives_extend() {
+        let mut hash_table = HashTable::with_layout(36, 8);
+
+        // insert 100 items, enough to extend the table twice
+        for i in 0..100 {
+            let key = format!("550e8400-e29b-41d4-a716-4466554{:05}", i);
+            hash_table.set(&key, &i.to_string()).unwrap();
+        }
+
+        for i in 0..100 {
+            let key = format!("550e8400-e29b-41d4-a716-4466554{:05}", i);
+            assert_eq!(hash_table.get(&key), Some(i.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_set_rejects_oversized_input() {
+        let mut hash_table = HashTable::new();
+
+        let long_key = "k".repeat(33);
+        let long_value = "v".repeat(97);
+
+        assert_eq!(
+            hash_table.set(&long_key, "value"),
+            Err(SetError::KeyTooLong { max: 32, got: 33 })
+        );
+        assert_eq!(
+            hash_table.set("key", &long_value),
+            Err(SetError::ValueTooLong { max: 96, got: 97 })
+        );
+
+        // nothing was written for the rejected calls
+        assert_eq!(hash_table.no_of_taken, 0);
+        assert_eq!(hash_table.get("key"), None);
+
+        // two keys sharing a 32 byte prefix don't collapse into one
+        let first = format!("{}a", "p".repeat(32));
+        let second = format!("{}b", "p".repeat(32));
+
+        assert!(hash_table.set(&first, "1").is_err());
+        assert!(hash_table.set(&second, "2").is_err());
+        assert_eq!(hash_table.get(&"p".repeat(32)), None);
+    }
+
+    #[test]
+    fn test_contains_key() {
+        let mut hash_table = HashTable::new();
+
+        hash_table.set("present", "value").unwrap();
+
+        assert!(hash_table.contains_key("present"));
+        assert!(!hash_table.contains_key("missing"));
+
+        hash_table.del("present");
+        assert!(!hash_table.contains_key("present"));
+    }
+
+    #[test]
+    fn test_iter_items() {
+        let mut hash_table = HashTable::new();
+
+        // insert 50 items and delete a few more to leave tombstones behind
+        for i in 0..55 {
+            let key = i.to_string();
+            hash_table.set(&key, &format!("value_{i}")).unwrap();
+        }
+
+        for i in 50..55 {
+            hash_table.del(&i.to_string());
+        }
+
+        let items: std::collections::HashSet<(String, String)> = hash_table.iter().collect();
+
+        assert_eq!(items.len(), 50);
+
+        for i in 0..50 {
+            assert!(items.contains(&(i.to_string(), format!("value_{i}"))));
+        }
+
+        assert_eq!(hash_table.keys().count(), 50);
+        assert!(hash_table.values().all(|value| value.starts_with("value_")));
+    }
+
+    #[test]
+    fn test_get_value_bytes() {
+        let mut hash_table = HashTable::new();
+
+        hash_table.set("name", "value").unwrap();
+        hash_table.set_bytes("binary", b"ab\0\0").unwrap();
+
+        assert_eq!(hash_table.get_value_bytes("name"), Some("value".as_bytes()));
+        assert_eq!(hash_table.get_value_bytes("binary"), Some(&b"ab\0\0"[..]));
+        assert_eq!(hash_table.get_value_bytes("missing"), None);
+    }
+
+    #[test]
+    fn test_bytes_with_nul() {
+        let mut hash_table = HashTable::new();
+
+        let value = b"ab\0cd\0\0";
+
+        hash_table.set_bytes("binary", value).unwrap();
+        assert_eq!(hash_table.get_bytes("binary"), Some(value.to_vec()));
+
+        // survives a rehash into a bigger table
+        for i in 0..50 {
+            let key = i.to_string();
+            hash_table.set(&key, &key).unwrap();
+        }
+
+        assert_eq!(hash_table.get_bytes("binary"), Some(value.to_vec()));
+        assert_eq!(hash_table.get_bytes("missing"), None);
+
+        // trailing NULs in `&str` values are kept as well
+        hash_table.set("text", "value\0").unwrap();
+        assert_eq!(hash_table.get("text"), Some(String::from("value\0")));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join("file_hash_test_save_and_load.tc");
+
+        let mut hash_table = HashTable::with_layout(36, 16);
+
+        for i in 0..50 {
+            let key = i.to_string();
+            hash_table.set(&key, &format!("value_{i}")).unwrap();
+        }
+
+        hash_table.del("7");
+        hash_table.save_to(&path).unwrap();
+
+        let loaded = HashTable::load_from(&path).unwrap();
+
+        assert_eq!(loaded.size, hash_table.size);
+        assert_eq!(loaded.no_of_taken, 49);
+        assert_eq!(loaded.get("7"), None);
+
+        for i in (0..50).filter(|&i| i != 7) {
+            assert_eq!(loaded.get(&i.to_string()), Some(format!("value_{i}")));
+        }
+
+        // Cleanup
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_load_rejects_truncated_file() {
+        let path = std::env::temp_dir().join("file_hash_test_load_truncated.tc");
+
+        let mut hash_table = HashTable::new();
+        hash_table.set("key", "value").unwrap();
+        hash_table.save_to(&path).unwrap();
+
+        // drop the last bucket
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+
+        let err = HashTable::load_from(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // too short for the header
+        fs::write(&path, [0u8; 4]).unwrap();
+
+        let err = HashTable::load_from(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Cleanup
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_len() {
+        let mut hash_table = HashTable::new();
+        assert!(hash_table.is_empty());
+
+        for i in 0..40 {
+            hash_table.set(&i.to_string(), "value").unwrap();
+        }
+
+        // updating a key doesn't change the count
+        hash_table.set("0", "other").unwrap();
+        assert_eq!(hash_table.len(), 40);
+
+        hash_table.del("0");
+        assert_eq!(hash_table.len(), 39);
+        assert!(!hash_table.is_empty());
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut hash_table = HashTable::new();
+
+        for i in 0..40 {
+            hash_table.set(&i.to_string(), "value").unwrap();
+        }
+
+        let size = hash_table.size;
+
+        hash_table.clear();
+
+        assert_eq!(hash_table.len(), 0);
+        assert_eq!(hash_table.size, size);
+        assert_eq!(hash_table.get("0"), None);
+        assert_eq!(hash_table.iter().count(), 0);
+
+        // still usable afterwards
+        hash_table.set("0", "again").unwrap();
+        assert_eq!(hash_table.get("0"), Some(String::from("again")));
+    }
+
+    #[test]
+    fn test_compact_stops_at_initial_size() {
+        let mut hash_table = HashTable::new();
+
+        for i in 0..100 {
+            hash_table.set(&i.to_string(), "value").unwrap();
+        }
+
+        // delete down to a single item, then the last one too
+        for i in 1..100 {
+            hash_table.del(&i.to_string());
+        }
+
+        assert_eq!(hash_table.len(), 1);
+        assert_eq!(hash_table.size, INITIAL_SIZE);
+        assert_eq!(hash_table.get("0"), Some(String::from("value")));
+
+        hash_table.del("0");
+
+        assert_eq!(hash_table.size, INITIAL_SIZE);
+        assert_eq!(hash_table.get("0"), None);
+
+        hash_table.set("0", "again").unwrap();
+        assert_eq!(hash_table.get("0"), Some(String::from("again")));
+    }
+
+    #[test]
+    fn test_set_returns_previous_value() {
+        let mut hash_table = HashTable::new();
+
+        assert_eq!(hash_table.set("key", "first"), Ok(None));
+        assert_eq!(
+            hash_table.set("key", "second"),
+            Ok(Some(String::from("first")))
+        );
+        assert_eq!(hash_table.len(), 1);
+
+        assert_eq!(
+            hash_table.set_bytes("key", b"third"),
+            Ok(Some(b"second".to_vec()))
+        );
+        assert_eq!(hash_table.set("other", "value"), Ok(None));
+        assert_eq!(hash_table.len(), 2);
+
+        // a deleted key is a fresh insert again
+        hash_table.del("key");
+        assert_eq!(hash_table.set("key", "fourth"), Ok(None));
+        assert_eq!(hash_table.len(), 2);
+    }
+
+    #[test]
+    fn test_case_insensitive() {
+        let mut hash_table = HashTable::new_case_insensitive();
+
+        hash_table.set("Content-Type", "text/plain").unwrap();
+
+        assert_eq!(
+            hash_table.get_hash_index("Content-Type"),
+            hash_table.get_hash_index("content-type")
+        );
+        assert_eq!(
+            hash_table.get("content-type"),
+            Some(String::from("text/plain"))
+        );
+        assert_eq!(
+            hash_table.get("CONTENT-TYPE"),
+            Some(String::from("text/plain"))
+        );
+        assert!(hash_table.contains_key("content-TYPE"));
+
+        // an update in another case replaces the same entry
+        assert_eq!(
+            hash_table.set("content-type", "text/html").unwrap(),
+            Some(String::from("text/plain"))
+        );
+        assert_eq!(hash_table.len(), 1);
+        assert_eq!(hash_table.keys().collect::<Vec<_>>(), vec!["content-type"]);
+
+        assert_eq!(
+            hash_table.del("CONTENT-type"),
+            Some(String::from("text/html"))
+        );
+        assert!(hash_table.is_empty());
+
+        // the default table still tells them apart
+        let mut hash_table = HashTable::new();
+
+        hash_table.set("Content-Type", "text/plain").unwrap();
+        assert_eq!(hash_table.get("content-type"), None);
+    }
+
+    #[test]
+    fn test_get_many() {
+        let mut hash_table = HashTable::new();
+
+        hash_table.set("a", "1").unwrap();
+        hash_table.set("b", "2").unwrap();
+        hash_table.set("c", "3").unwrap();
+
+        assert_eq!(
+            hash_table.get_many(&["c", "missing", "a", "a", "b"]),
+            vec![
+                Some(String::from("3")),
+                None,
+                Some(String::from("1")),
+                Some(String::from("1")),
+                Some(String::from("2")),
+            ]
+        );
+        assert!(hash_table.get_many(&[]).is_empty());
+
+        // keys further down a chain, past a deleted one
+        let home = hash_table.get_hash_index("a");
+        let colliding: Vec<String> = (0..)
+            .map(|i| i.to_string())
+            .filter(|key| hash_table.get_hash_index(key) == home)
+            .take(3)
+            .collect();
+
+        for key in &colliding {
+            hash_table.set(key, key).unwrap();
+        }
+
+        hash_table.del(&colliding[0]);
+
+        // a key which spilled over into the overflow list
+        let mut spilled = HashTable::with_max_probe(1);
+
+        let home = spilled.get_hash_index("a");
+        let spilling: Vec<String> = (0..)
+            .map(|i| i.to_string())
+            .filter(|key| spilled.get_hash_index(key) == home)
+            .take(2)
+            .collect();
+
+        spilled.set(&spilling[0], "home").unwrap();
+        spilled.set(&spilling[1], "overflow").unwrap();
+        assert_eq!(spilled.overflow.len(), 1);
+
+        assert_eq!(
+            spilled.get_many(&[&spilling[1], &spilling[0]]),
+            vec![Some(String::from("overflow")), Some(String::from("home"))]
+        );
+
+        let long_key = "k".repeat(KEY_SIZE + 1);
+
+        assert_eq!(
+            hash_table.get_many(&[&colliding[0], &colliding[2], &long_key, "a\0"]),
+            vec![None, Some(colliding[2].clone()), None, None]
+        );
+
+        // case insensitive keys still match in any case
+        let mut hash_table = HashTable::new_case_insensitive();
+        hash_table.set("Key", "value").unwrap();
+
+        assert_eq!(
+            hash_table.get_many(&["KEY", "key", "other"]),
+            vec![
+                Some(String::from("value")),
+                Some(String::from("value")),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_probe_stats() {
+        let mut hash_table = HashTable::new();
+
+        let empty = hash_table.probe_stats();
+        assert_eq!(empty.average_probe_len, 0.0);
+        assert_eq!(empty.max_probe_len, 0);
+
+        // three keys fighting over the same home slot
+        let home = hash_table.get_hash_index("0");
+        let keys: Vec<String> = (0..)
+            .map(|i: usize| i.to_string())
+            .filter(|key| hash_table.get_hash_index(key) == home)
+            .take(3)
+            .collect();
+
+        for key in &keys {
+            hash_table.set(key, "value").unwrap();
+        }
+
+        let stats = hash_table.probe_stats();
+
+        assert_eq!(stats.max_probe_len, 2);
+        assert!(stats.max_probe_len > 1);
+        assert_eq!(stats.average_probe_len, 1.0);
+        assert_eq!(stats.occupancy, 3.0 / 32.0);
+    }
+
+    #[test]
+    fn test_new_seeded() {
+        let mut first = HashTable::new_seeded(1);
+        let mut second = HashTable::new_seeded(2);
+
+        assert_ne!(first.get_hash_index("key"), second.get_hash_index("key"));
+
+        // same seed, same slot
+        assert_eq!(
+            first.get_hash_index("key"),
+            HashTable::new_seeded(1).get_hash_index("key")
+        );
+
+        // a seed of 0 is plain djb2
+        assert_eq!("a".hash_with_seed(0), 5381 * 33 + 'a' as usize);
+
+        first.set("key", "first").unwrap();
+        second.set("key", "second").unwrap();
+
+        assert_eq!(first.get("key"), Some(String::from("first")));
+        assert_eq!(second.get("key"), Some(String::from("second")));
+    }
+
+    #[test]
+    fn test_del_many() {
+        let mut hash_table = HashTable::new();
+
+        // five keys on the same home slot
+        let home = hash_table.get_hash_index("0");
+        let keys: Vec<String> = (0..)
+            .map(|i: usize| i.to_string())
+            .filter(|key| hash_table.get_hash_index(key) == home)
+            .take(5)
+            .collect();
+
+        for key in &keys {
+            hash_table.set(key, &format!("value {key}")).unwrap();
+        }
+
+        // keep the table above the compaction threshold
+        for i in 0..10 {
+            let key = format!("filler_{i}");
+            hash_table.set(&key, &key).unwrap();
+        }
+
+        let removed = hash_table.del_many(&[&keys[0], "missing", &keys[2], &keys[3]]);
+
+        assert_eq!(
+            removed,
+            vec![
+                Some(format!("value {}", keys[0])),
+                None,
+                Some(format!("value {}", keys[2])),
+                Some(format!("value {}", keys[3])),
+            ]
+        );
+        assert_eq!(hash_table.len(), 12);
+
+        assert_eq!(hash_table.get(&keys[1]), Some(format!("value {}", keys[1])));
+        assert_eq!(hash_table.get(&keys[4]), Some(format!("value {}", keys[4])));
+        assert_eq!(hash_table.get(&keys[0]), None);
+    }
+
+    #[test]
+    fn test_get_or() {
+        let mut hash_table = HashTable::new();
+        hash_table.set("key", "value").unwrap();
+
+        assert_eq!(hash_table.get_or("key", "fallback"), "value");
+        assert_eq!(hash_table.get_or("missing", "fallback"), "fallback");
+
+        assert_eq!(hash_table.get_or_default("key"), "value");
+        assert_eq!(hash_table.get_or_default("missing"), "");
+    }
+
+    #[test]
+    fn test_rehash() {
+        let mut hash_table = HashTable::new_seeded(0);
+
+        // five keys on the same home slot
+        let home = hash_table.get_hash_index("0");
+        let keys: Vec<String> = (0..)
+            .map(|i: usize| i.to_string())
+            .filter(|key| hash_table.get_hash_index(key) == home)
+            .take(5)
+            .collect();
+
+        // keep the table above the compaction threshold
+        for i in 0..5 {
+            let key = format!("filler_{i}");
+            hash_table.set(&key, &key).unwrap();
+        }
+
+        for key in &keys {
+            hash_table.set(key, "value").unwrap();
+        }
+
+        // only the last one is left, behind four tombstones
+        for key in &keys[..4] {
+            hash_table.del(key);
+        }
+
+        let before = hash_table.probe_stats();
+        let size = hash_table.size;
+
+        hash_table.rehash();
+
+        let after = hash_table.probe_stats();
+
+        assert!(after.max_probe_len < before.max_probe_len);
+        assert!(after.average_probe_len < before.average_probe_len);
+        assert_eq!(hash_table.size, size);
+        assert_eq!(hash_table.len(), 6);
+        assert_eq!(hash_table.get(&keys[4]), Some(String::from("value")));
+        assert!(hash_table
+            .items()
+            .all(|item| !item.key_matches(&keys[0], false)));
+        assert!(!hash_table
+            .kvs
+            .chunks_exact(hash_table.bucket_size())
+            .any(|b| b[0] == TOMBSTONE));
+    }
+
+    #[test]
+    fn test_try_set() {
+        let mut hash_table = HashTable::new();
+
+        assert_eq!(hash_table.try_set("key", "first"), Ok(None));
+        assert_eq!(
+            hash_table.try_set("key", "second"),
+            Ok(Some(String::from("first")))
+        );
+        assert_eq!(
+            hash_table.try_set(&"k".repeat(KEY_SIZE + 1), "value"),
+            Err(StoreError::Set(SetError::KeyTooLong {
+                max: KEY_SIZE,
+                got: KEY_SIZE + 1
+            }))
+        );
+
+        let mut hash_table = HashTable::with_truncated_buffer();
+
+        assert!(matches!(
+            hash_table.try_set("key", "value"),
+            Err(StoreError::OutOfBounds { len: 0, .. })
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn test_set_panics_on_truncated_buffer() {
+        let mut hash_table = HashTable::with_truncated_buffer();
+
+        let _ = hash_table.set("key", "value");
+    }
+
+    #[test]
+    fn test_keys_with_prefix() {
+        let mut hash_table = HashTable::new();
+
+        hash_table.set("user:1", "alice").unwrap();
+        hash_table.set("user:2", "bob").unwrap();
+        hash_table.set("admin:1", "carol").unwrap();
+
+        let mut keys = hash_table.keys_with_prefix("user:");
+        keys.sort();
+
+        assert_eq!(keys, vec!["user:1", "user:2"]);
+        assert_eq!(hash_table.keys_with_prefix("admin:"), vec!["admin:1"]);
+        assert!(hash_table.keys_with_prefix("guest:").is_empty());
+        assert_eq!(hash_table.keys_with_prefix("").len(), 3);
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut hash_table = HashTable::new();
+
+        hash_table.set("name", "value").unwrap();
+        hash_table.set("other", "value").unwrap();
+
+        let mut cloned = hash_table.clone();
+
+        cloned.set("name", "changed").unwrap();
+        cloned.del("other");
+        cloned.set("new", "value").unwrap();
+
+        assert_eq!(hash_table.get("name"), Some(String::from("value")));
+        assert_eq!(hash_table.get("other"), Some(String::from("value")));
+        assert_eq!(hash_table.get("new"), None);
+        assert_eq!(hash_table.len(), 2);
+
+        assert_eq!(cloned.get("name"), Some(String::from("changed")));
+        assert_eq!(cloned.len(), 2);
+    }
+
+    #[test]
+    fn test_memory_footprint() {
+        let mut hash_table = HashTable::new();
+        hash_table.set("name", "value").unwrap();
+
+        let footprint = hash_table.memory_footprint();
+        assert!(footprint >= INITIAL_SIZE * (KEY_SIZE + LEN_SIZE + VALUE_SIZE));
+
+        hash_table.extend().unwrap();
+
+        assert!(hash_table.memory_footprint() > footprint);
+    }
+
+    #[test]
+    fn test_probe_strategy() {
+        // keys sharing one home slot, found for each table on its own
+        fn clustered_keys(hash_table: &HashTable) -> Vec<String> {
+            let home = hash_table.get_hash_index("0");
+
+            (0..)
+                .map(|i: usize| i.to_string())
+                .filter(|key| hash_table.get_hash_index(key) == home)
+                .take(12)
+                .collect()
+        }
+
+        let mut linear = HashTable::new_seeded(0);
+        let mut double = HashTable {
+            seed: 0,
+            ..HashTable::with_probe_strategy(ProbeStrategy::Double)
+        };
+
+        for key in clustered_keys(&linear) {
+            linear.set(&key, "value").unwrap();
+        }
+
+        for key in clustered_keys(&double) {
+            double.set(&key, "value").unwrap();
+        }
+
+        // one after the other behind the home slot, 0 to 11 steps
+        assert_eq!(linear.probe_stats().average_probe_len, 5.5);
+        assert!(double.probe_stats().average_probe_len < 2.0);
+
+        for key in clustered_keys(&double) {
+            assert_eq!(double.get(&key), Some(String::from("value")));
+        }
+
+        // the strategy is kept when the table grows
+        for i in 0..50 {
+            double.set(&format!("key_{i}"), "value").unwrap();
+        }
+
+        assert_eq!(double.probe_strategy, ProbeStrategy::Double);
+        assert_eq!(double.get("key_0"), Some(String::from("value")));
+
+        // and saved with it
+        let path = std::env::temp_dir().join("file_hash_test_probe_strategy.tc");
+        double.save_to(&path).unwrap();
+
+        let loaded = HashTable::load_from(&path).unwrap();
+        assert_eq!(loaded.probe_strategy, ProbeStrategy::Double);
+        assert_eq!(loaded.get("key_0"), Some(String::from("value")));
+
+        // Cleanup
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_pop() {
+        let mut hash_table = HashTable::new();
+
+        hash_table.set("job", "payload").unwrap();
+        hash_table.set("other", "value").unwrap();
+
+        assert_eq!(hash_table.pop("job"), Some(String::from("payload")));
+        assert_eq!(hash_table.pop("job"), None);
+        assert_eq!(hash_table.get("job"), None);
+        assert_eq!(hash_table.len(), 1);
+
+        // popping down to empty doesn't compact
+        for i in 0..40 {
+            hash_table.set(&i.to_string(), "value").unwrap();
+        }
+
+        let size = hash_table.size;
+        assert!(size > INITIAL_SIZE);
+
+        for i in 0..40 {
+            assert_eq!(hash_table.pop(&i.to_string()), Some(String::from("value")));
+        }
+
+        assert_eq!(hash_table.size, size);
+        assert_eq!(hash_table.get("other"), Some(String::from("value")));
+    }
+
+    #[test]
+    fn test_update() {
+        let mut hash_table = HashTable::new();
+
+        hash_table.set("name", "value").unwrap();
+
+        assert!(hash_table.update("name", |value| value.push('x')));
+        assert_eq!(hash_table.get("name"), Some(String::from("valuex")));
+        assert_eq!(hash_table.len(), 1);
+
+        assert!(!hash_table.update("missing", |value| value.push('x')));
+        assert_eq!(hash_table.get("missing"), None);
+
+        // cut back to the value size, never inside a char
+        assert!(hash_table.update("name", |value| {
+            *value = "a".repeat(VALUE_SIZE - 1) + "é";
+        }));
+        assert_eq!(hash_table.get("name"), Some("a".repeat(VALUE_SIZE - 1)));
+    }
+
+    #[test]
+    fn test_resize_to() {
+        let mut hash_table = HashTable::new();
+
+        for i in 0..30 {
+            hash_table.set(&i.to_string(), "value").unwrap();
+        }
+
+        assert_eq!(hash_table.size, 64);
+
+        hash_table.resize_to(128).unwrap();
+        assert_eq!(hash_table.size, 128);
+
+        for i in 0..30 {
+            assert_eq!(hash_table.get(&i.to_string()), Some(String::from("value")));
+        }
+
+        // 30 keys reach the load factor of 41 buckets
+        assert_eq!(
+            hash_table.resize_to(41),
+            Err(ResizeError::TooSmall { size: 41, len: 30 })
+        );
+        assert_eq!(hash_table.size, 128);
+
+        hash_table.resize_to(42).unwrap();
+        assert_eq!(hash_table.len(), 30);
+        assert_eq!(hash_table.get("29"), Some(String::from("value")));
+    }
+
+    #[test]
+    fn test_set_rejects_nul_in_key() {
+        let mut hash_table = HashTable::new();
+
+        assert_eq!(
+            hash_table.set("a\0", "value"),
+            Err(SetError::KeyContainsNul)
+        );
+        assert_eq!(
+            hash_table.set_bytes("\0a", b"value"),
+            Err(SetError::KeyContainsNul)
+        );
+        assert!(hash_table.is_empty());
+
+        assert_eq!(hash_table.set("a", "value"), Ok(None));
+        assert_eq!(hash_table.get("a"), Some(String::from("value")));
+    }
+
+    #[test]
+    fn test_rename() {
+        let mut hash_table = HashTable::new_seeded(0);
+
+        // a chain of colliding keys, "0" sits in front of the others
+        let home = hash_table.get_hash_index("0");
+        let keys: Vec<String> = (0..)
+            .map(|i: usize| i.to_string())
+            .filter(|key| hash_table.get_hash_index(key) == home)
+            .take(3)
+            .collect();
+
+        for key in &keys {
+            hash_table.set(key, &format!("value {key}")).unwrap();
+        }
+
+        assert!(hash_table.rename("0", "renamed"));
+
+        assert_eq!(hash_table.get("0"), None);
+        assert_eq!(hash_table.get("renamed"), Some(String::from("value 0")));
+        assert_eq!(hash_table.len(), 3);
+
+        // keys behind the vacated slot are still found
+        for key in &keys[1..] {
+            assert_eq!(hash_table.get(key), Some(format!("value {key}")));
+        }
+
+        assert!(!hash_table.rename("missing", "other"));
+        assert!(!hash_table.rename("renamed", &keys[1]));
+        assert!(!hash_table.rename("renamed", &"k".repeat(KEY_SIZE + 1)));
+        assert_eq!(hash_table.get("renamed"), Some(String::from("value 0")));
+    }
+
+    #[test]
+    fn test_empty_value() {
+        let mut hash_table = HashTable::new();
+
+        hash_table.set("empty", "").unwrap();
+
+        // the stored length is 0, which is not the same as no bucket
+        assert_eq!(hash_table.get("empty"), Some(String::new()));
+        assert_eq!(hash_table.get_value_bytes("empty"), Some(&b""[..]));
+        assert_eq!(hash_table.get("missing"), None);
+        assert!(hash_table.contains_key("empty"));
+
+        // and it survives the table being rebuilt
+        for i in 0..50 {
+            hash_table.set(&i.to_string(), "value").unwrap();
+        }
+
+        assert_eq!(hash_table.get("empty"), Some(String::new()));
+        assert_eq!(hash_table.del("empty"), Some(String::new()));
+        assert_eq!(hash_table.get("empty"), None);
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_get_many() {
+        let mut hash_table = HashTable::new();
+
+        for i in 0..1000 {
+            hash_table
+                .set(&i.to_string(), &format!("value {i}"))
+                .unwrap();
+        }
+
+        // every other key is missing
+        let keys: Vec<String> = (0..2000).step_by(2).map(|i: usize| i.to_string()).collect();
+        let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+
+        let expected: Vec<Option<String>> = keys.iter().map(|key| hash_table.get(key)).collect();
+
+        assert_eq!(hash_table.par_get_many(&keys), expected);
+    }
+
+    #[test]
+    fn test_to_hashmap() {
+        let mut hash_table = HashTable::new();
+
+        for i in 0..20 {
+            hash_table
+                .set(&i.to_string(), &format!("value {i}"))
+                .unwrap();
+        }
+
+        hash_table.del("7");
+
+        let map = hash_table.to_hashmap();
+
+        assert_eq!(map.len(), hash_table.len());
+        assert_eq!(map.get("3").map(String::as_str), Some("value 3"));
+        assert_eq!(map.get("7"), None);
+
+        // and back again
+        let mut round_trip = HashTable::new();
+
+        for (key, value) in &map {
+            round_trip.set(key, value).unwrap();
+        }
+
+        assert_eq!(round_trip.len(), hash_table.len());
+        assert_eq!(round_trip.get("3"), hash_table.get("3"));
+    }
+
+    #[test]
+    fn test_max_probe() {
+        let path = std::env::temp_dir().join("file_hash_test_max_probe.tc");
+        let _ = fs::remove_file(&path);
+
+        let mut hash_table = HashTable::with_max_probe(2);
+
+        // ten keys on the same home slot, only two fit in the budget
+        let home = hash_table.get_hash_index("0");
+        let keys: Vec<String> = (0..)
+            .map(|i: usize| i.to_string())
+            .filter(|key| hash_table.get_hash_index(key) == home)
+            .take(10)
+            .collect();
+
+        for key in &keys {
+            hash_table.set(key, &format!("value {key}")).unwrap();
+        }
+
+        assert_eq!(hash_table.no_of_taken, 2);
+        assert_eq!(hash_table.overflow.len(), 8);
+        assert_eq!(hash_table.len(), 10);
+        assert_eq!(hash_table.probe_stats().max_probe_len, 1);
+
+        for key in &keys {
+            assert_eq!(hash_table.get(key), Some(format!("value {key}")));
+        }
+
+        // updating an overflowed key doesn't add another copy
+        assert_eq!(
+            hash_table.set(&keys[9], "updated"),
+            Ok(Some(format!("value {}", keys[9])))
+        );
+        assert_eq!(hash_table.len(), 10);
+        assert_eq!(hash_table.get(&keys[9]), Some(String::from("updated")));
+
+        assert_eq!(hash_table.del(&keys[5]), Some(format!("value {}", keys[5])));
+        assert_eq!(hash_table.get(&keys[5]), None);
+        assert_eq!(hash_table.iter().count(), 9);
+
+        // overflowed pairs survive a save and load
+        hash_table.save_to(&path).unwrap();
+        let loaded = HashTable::load_from(&path).unwrap();
+
+        assert_eq!(loaded.max_probe, 2);
+        assert_eq!(loaded.len(), 9);
+        assert_eq!(loaded.get(&keys[9]), Some(String::from("updated")));
+        assert_eq!(loaded.get(&keys[8]), Some(format!("value {}", keys[8])));
+
+        // Cleanup
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut hash_table = HashTable::new();
+        assert_eq!(hash_table.capacity(), INITIAL_SIZE);
+
+        for i in 0..40 {
+            hash_table.set(&i.to_string(), "value").unwrap();
+        }
+
+        assert_eq!(hash_table.capacity(), INITIAL_SIZE * 2);
+        assert_eq!(hash_table.len(), 40);
+
+        hash_table.resize_to(128).unwrap();
+        assert_eq!(hash_table.capacity(), 128);
+    }
+
+    #[test]
+    fn test_append() {
+        let mut hash_table = HashTable::new();
+
+        // a missing key starts out as the suffix
+        hash_table.append("log", "first").unwrap();
+        hash_table.append("log", ", second").unwrap();
+        hash_table.append("log", ", third").unwrap();
+
+        assert_eq!(
+            hash_table.get("log"),
+            Some(String::from("first, second, third"))
+        );
+        assert_eq!(hash_table.len(), 1);
+
+        // past the value size nothing is written
+        let suffix = "x".repeat(VALUE_SIZE);
+
+        assert_eq!(
+            hash_table.append("log", &suffix),
+            Err(StoreError::Set(SetError::ValueTooLong {
+                max: VALUE_SIZE,
+                got: 20 + VALUE_SIZE,
+            }))
+        );
+        assert_eq!(
+            hash_table.get("log"),
+            Some(String::from("first, second, third"))
+        );
+
+        // right up to the value size still fits
+        hash_table.append("log", &suffix[20..]).unwrap();
+        assert_eq!(hash_table.get("log").unwrap().len(), VALUE_SIZE);
+    }
+
+    #[test]
+    fn test_save_and_load_case_insensitive() {
+        let path = std::env::temp_dir().join("file_hash_test_save_case_insensitive.tc");
+        let _ = fs::remove_file(&path);
+
+        let mut hash_table = HashTable::new_case_insensitive();
+
+        for i in 0..20 {
+            hash_table.set(&format!("Key{i}"), &i.to_string()).unwrap();
+        }
+
+        hash_table.save_to(&path).unwrap();
+
+        let loaded = HashTable::load_from(&path).unwrap();
+
+        for i in 0..20 {
+            assert_eq!(loaded.get(&format!("key{i}")), Some(i.to_string()));
+            assert_eq!(loaded.get(&format!("KEY{i}")), Some(i.to_string()));
+        }
+
+        // the mode is a 0 or 1 flag, the 8th header field
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[7 * 8] = 2;
+        fs::write(&path, bytes).unwrap();
+
+        let err = HashTable::load_from(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Cleanup
+        let _ = fs::remove_file(&path);
     }
 }
@@ -1,8 +1,17 @@
-trait Hashable {
+use std::borrow::Borrow;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::marker::PhantomData;
+use std::path::Path;
+
+/// Places a key in the table, implement it to key the table on
+/// your own type with its own mixing
+pub trait Hashable {
     fn hash(&self) -> usize;
 }
 
-impl Hashable for &str {
+impl Hashable for str {
     // using the djb2 algo (https://theartincode.stanis.me/008-djb2/)
     fn hash(&self) -> usize {
         let mut result: usize = 5381;
@@ -15,11 +24,74 @@ impl Hashable for &str {
     }
 }
 
+impl Hashable for String {
+    fn hash(&self) -> usize {
+        self.as_str().hash()
+    }
+}
+
+// Every bucket is `BUCKET_SIZE` bytes, laid out as
+//
+// single - [index | key | value]
+// index  - [index | key | indexes]
+// shard  - [index | value]
+const BUCKET_SIZE: usize = 32;
+const KEY_SIZE: usize = 16;
+
+// value bytes held by a single item bucket and by a shard
+const SINGLE_VALUE_SIZE: usize = BUCKET_SIZE - 1 - KEY_SIZE;
+const SHARD_SIZE: usize = BUCKET_SIZE - 1;
+
+// an index bucket references at most 2 shards
+const MAX_VALUE_SIZE: usize = 2 * SHARD_SIZE;
+
+// shard slots are stored as u32, so tables can grow past 65_535 buckets
+const SHARD_INDEX_SIZE: usize = 4;
+
+// saved tables start with size, no_of_taken and the prime size flag as
+// little endian u64s, followed by the raw buckets
+const HEADER_SIZE: usize = 3 * 8;
+
+/// Why `set` couldn't store a key
+#[derive(Debug, PartialEq, Eq)]
+pub enum BucketError {
+    KeyTooLong {
+        max: usize,
+        got: usize,
+    },
+    ValueTooLong {
+        max: usize,
+        got: usize,
+    },
+    /// Keys are padded with `\0`, one inside the key would make it
+    /// match another key
+    KeyContainsNul,
+    /// No empty bucket left for the key or for its shards
+    TableFull,
+}
+
+impl fmt::Display for BucketError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BucketError::KeyTooLong { max, got } => {
+                write!(f, "key is {got} bytes, max allowed is {max}")
+            }
+            BucketError::ValueTooLong { max, got } => {
+                write!(f, "value is {got} bytes, max allowed is {max}")
+            }
+            BucketError::KeyContainsNul => write!(f, "key contains a NUL byte"),
+            BucketError::TableFull => write!(f, "no empty bucket left"),
+        }
+    }
+}
+
+impl std::error::Error for BucketError {}
+
 struct Bucket {
     // index - u8 [0 - NULL; 1 - last; 2 - single; 3 - index; 4..n - shards]
-    // key - [u8; 3]
-    // value - [u8; 7] or [u8; 4]
-    // indexes - [u16; 2]
+    // key - [u8; KEY_SIZE]
+    // value - [u8; SHARD_SIZE] or [u8; SINGLE_VALUE_SIZE]
+    // indexes - [u32; 2]
 }
 
 impl Bucket {
@@ -27,63 +99,66 @@ impl Bucket {
     // value bucket
     // single item bucket
 
-    fn _index_bucket(key: &str, indexes: &Vec<u16>) -> [u8; 8] {
-        let mut buffer = [b'\0'; 8];
+    fn _index_bucket(key: &str, indexes: &[u32]) -> [u8; BUCKET_SIZE] {
+        let mut buffer = [b'\0'; BUCKET_SIZE];
 
         assert!(indexes.len() <= 2, "Can only contain 2 indexes at max");
 
         let mut key_bytes = Vec::from(key.as_bytes());
-        key_bytes.resize(3, b'\0');
+        key_bytes.resize(KEY_SIZE, b'\0');
 
-        let index = (3 as u8).to_le_bytes();
+        let index = 3_u8.to_le_bytes();
+        let start = 1 + KEY_SIZE;
 
         buffer[0..1].clone_from_slice(&index);
-        buffer[1..4].clone_from_slice(&key_bytes);
-        buffer[4..6].clone_from_slice(&indexes[0].to_le_bytes());
+        buffer[1..start].clone_from_slice(&key_bytes);
+        let end = start + SHARD_INDEX_SIZE;
+
+        buffer[start..end].clone_from_slice(&indexes[0].to_le_bytes());
 
         if indexes.len() == 1 {
-            let empty_buffer = [b'\0'; 2];
-            buffer[6..8].clone_from_slice(&empty_buffer);
+            let empty_buffer = [b'\0'; SHARD_INDEX_SIZE];
+            buffer[end..(end + SHARD_INDEX_SIZE)].clone_from_slice(&empty_buffer);
         } else {
-            buffer[6..8].clone_from_slice(&indexes[1].to_le_bytes());
+            buffer[end..(end + SHARD_INDEX_SIZE)].clone_from_slice(&indexes[1].to_le_bytes());
         }
 
         buffer
     }
 
-    fn _value_bucket(index: u8, value: [u8; 7]) -> [u8; 8] {
-        let mut buffer = [b'\0'; 8];
+    fn _value_bucket(index: u8, value: [u8; SHARD_SIZE]) -> [u8; BUCKET_SIZE] {
+        let mut buffer = [b'\0'; BUCKET_SIZE];
 
         let index = index.to_le_bytes();
 
         buffer[0..1].clone_from_slice(&index);
-        buffer[1..8].clone_from_slice(&value);
+        buffer[1..BUCKET_SIZE].clone_from_slice(&value);
 
         buffer
     }
 
-    fn _single_item_bucket(key: &str, value: &str) -> [u8; 8] {
-        let mut buffer = [b'\0'; 8];
+    fn _single_item_bucket(key: &str, value: &str) -> [u8; BUCKET_SIZE] {
+        let mut buffer = [b'\0'; BUCKET_SIZE];
 
         let mut key_bytes = Vec::from(key.as_bytes());
-        key_bytes.resize(3, b'\0');
+        key_bytes.resize(KEY_SIZE, b'\0');
 
         let mut value_bytes = Vec::from(value.as_bytes());
-        value_bytes.resize(4, b'\0');
+        value_bytes.resize(SINGLE_VALUE_SIZE, b'\0');
 
-        let index = (2 as u8).to_le_bytes();
+        let index = 2_u8.to_le_bytes();
 
         buffer[0..1].clone_from_slice(&index);
-        buffer[1..4].clone_from_slice(&key_bytes);
-        buffer[4..8].clone_from_slice(&value_bytes);
+        buffer[1..(1 + KEY_SIZE)].clone_from_slice(&key_bytes);
+        buffer[(1 + KEY_SIZE)..BUCKET_SIZE].clone_from_slice(&value_bytes);
 
         buffer
     }
 
-    fn _split_value(value: Vec<u8>) -> Vec<[u8; 7]> {
-        let mut chunks: Vec<[u8; 7]> = Vec::new();
+    fn _split_value(value: Vec<u8>) -> Vec<[u8; SHARD_SIZE]> {
+        let mut chunks: Vec<[u8; SHARD_SIZE]> = Vec::new();
 
-        let chunk_size = 7;
+        let chunk_size = SHARD_SIZE;
 
         let val_len = value.len();
 
@@ -105,90 +180,305 @@ impl Bucket {
     }
 }
 
-pub struct HashTable {
+/// Keys are stored as their string form, `K` only decides where
+/// they're placed through its `Hashable` impl
+pub struct HashTable<K = String> {
     _kvs: Vec<u8>,
     size: usize,
     _no_of_taken: usize,
+    // grow to primes instead of powers of two
+    prime_size: bool,
+    _key: PhantomData<K>,
+}
+
+// by hand, a derive would require `K: Clone` for the marker
+impl<K> Clone for HashTable<K> {
+    fn clone(&self) -> Self {
+        Self {
+            _kvs: self._kvs.clone(),
+            size: self.size,
+            _no_of_taken: self._no_of_taken,
+            prime_size: self.prime_size,
+            _key: PhantomData,
+        }
+    }
+}
+
+impl<K> Default for HashTable<K> {
+    fn default() -> Self {
+        Self::with_size(32, false)
+    }
 }
 
 impl HashTable {
     pub fn new() -> Self {
-        let size = 32;
+        Self::default()
+    }
+
+    /// Create a table which is always sized to a prime, so the
+    /// modulus of the hash mixes in all of its bits
+    pub fn with_prime_size() -> Self {
+        Self::with_size(next_prime(32), true)
+    }
 
+    /// Create a table which holds `n` single item entries without
+    /// extending. A sharded value takes a bucket per shard on top of
+    /// its index bucket, so those can still make it extend earlier
+    pub fn with_capacity(n: usize) -> Self {
+        // `set` extends once the taken buckets reach 3/4 of the size
+        let size = ((n + 1) * 4).div_ceil(3);
+
+        Self::with_size(std::cmp::max(size, 32), false)
+    }
+}
+
+impl<K> HashTable<K> {
+    fn with_size(size: usize, prime_size: bool) -> Self {
         Self {
-            _kvs: vec![b'\0'; size * 8],
+            _kvs: vec![b'\0'; size * BUCKET_SIZE],
             size,
             _no_of_taken: 0,
+            prime_size,
+            _key: PhantomData,
         }
     }
 
-    pub fn set(&mut self, key: &str, value: &str) {
-        let mut index = self._get_hash_index(&key);
+    /// Number of buckets, taken or not. Unlike `len` it only changes
+    /// when the table is extended, and a sharded value takes up more
+    /// than one of them
+    pub fn capacity(&self) -> usize {
+        self.size
+    }
 
-        let value_bytes = Vec::from(value.as_bytes());
-        let key_bytes = Vec::from(key.as_bytes());
+    /// Write the table to `path` so it can be restored with `load`.
+    /// The buffer is written as is, shards are referenced by slot
+    /// so a sharded value is only readable in the slots it was set in
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut buffer = Vec::with_capacity(HEADER_SIZE + self._kvs.len());
 
-        if key_bytes.len() > 3 {
-            eprintln!("KEY should be smaller then 3");
-            return;
+        let fields = [
+            self.size as u64,
+            self._no_of_taken as u64,
+            self.prime_size as u64,
+        ];
+
+        for field in fields {
+            buffer.extend_from_slice(&field.to_le_bytes());
         }
 
-        let load_factor = (self.size as f64 * 0.75) as usize;
+        buffer.extend_from_slice(&self._kvs);
 
-        if (self._no_of_taken + value_bytes.len() + 1) >= load_factor {
-            self.extend();
+        fs::write(path, buffer)
+    }
+
+    /// Restore a table written by `save`, a truncated or mismatched
+    /// file is reported as `InvalidData`
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+
+        if bytes.len() < HEADER_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "File is too short to hold the header",
+            ));
         }
 
-        // TODO: If the loop is over and no index is found
-        // we got to handle the error 🥹
-        for _ in 0..self.size {
-            let offset = index * 8;
-            assert!(offset + 8 <= self._kvs.len(), "Index out of bounds");
+        let mut header = bytes[..HEADER_SIZE]
+            .chunks_exact(8)
+            .map(|field| u64::from_le_bytes(field.try_into().unwrap()));
 
-            let index_bytes: [u8; 1] = self._kvs[offset..(offset + 1)].try_into().unwrap();
+        let size = header.next().unwrap() as usize;
+        let no_of_taken = header.next().unwrap() as usize;
+        let prime_size = header.next().unwrap() != 0;
 
-            if index_bytes[0] == b'\0' {
-                // Found the index
-                break;
+        if size == 0 || no_of_taken > size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Header holds an invalid table layout",
+            ));
+        }
+
+        let kvs = &bytes[HEADER_SIZE..];
+
+        if size.checked_mul(BUCKET_SIZE) != Some(kvs.len()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Bucket data doesn't match the header",
+            ));
+        }
+
+        // `del` counts down from the header, so it has to match the buckets
+        let taken = kvs
+            .chunks_exact(BUCKET_SIZE)
+            .filter(|bucket| bucket[0] != 0)
+            .count();
+
+        if taken != no_of_taken {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Header doesn't match the taken buckets",
+            ));
+        }
+
+        // a shard is tagged as last (1) or with its position (4..n)
+        let is_shard = |slot: usize| slot < size && matches!(kvs[slot * BUCKET_SIZE], 1 | 4..);
+
+        // every index bucket has to point at shards inside the buffer,
+        // `get` follows them without checking
+        for bucket in kvs
+            .chunks_exact(BUCKET_SIZE)
+            .filter(|bucket| bucket[0] == 3)
+        {
+            let start = 1 + KEY_SIZE;
+
+            let mut indexes = bucket[start..(start + 2 * SHARD_INDEX_SIZE)]
+                .chunks_exact(SHARD_INDEX_SIZE)
+                .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()) as usize);
+
+            let first = indexes.next().unwrap();
+            let second = indexes.next().unwrap();
+
+            // the second index is only read if the first shard isn't the last
+            let valid = is_shard(first) && (kvs[first * BUCKET_SIZE] == 1 || is_shard(second));
+
+            if !valid {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Index bucket points at a missing shard",
+                ));
             }
+        }
 
-            let bucket_index = u8::from_le_bytes(index_bytes);
+        Ok(Self {
+            _kvs: kvs.to_vec(),
+            size,
+            _no_of_taken: no_of_taken,
+            prime_size,
+            _key: PhantomData,
+        })
+    }
+}
 
-            let key_bytes = &self._kvs[(offset + 1)..(offset + 4)];
+// `From<String>` turns the stored keys back into `K` when extending
+impl<K: Hashable + AsRef<str> + From<String>> HashTable<K> {
+    /// Insert or update a key, nothing is stored if the key or value
+    /// is too long or no free bucket is left for it
+    pub fn set<Q>(&mut self, key: &Q, value: &str) -> Result<(), BucketError>
+    where
+        K: Borrow<Q>,
+        Q: Hashable + AsRef<str> + ?Sized,
+    {
+        let value_bytes = Vec::from(value.as_bytes());
+        let key_bytes = Vec::from(key.as_ref().as_bytes());
 
-            let saved_key = String::from_utf8_lossy(key_bytes)
-                .trim_end_matches('\0')
-                .trim_start_matches('\0')
-                .to_string();
+        if key_bytes.contains(&b'\0') {
+            return Err(BucketError::KeyContainsNul);
+        }
+
+        // longer keys are rejected rather than truncated, so a
+        // multi-byte character is never split
+        if key_bytes.len() > KEY_SIZE {
+            return Err(BucketError::KeyTooLong {
+                max: KEY_SIZE,
+                got: key_bytes.len(),
+            });
+        }
+
+        if value_bytes.len() > MAX_VALUE_SIZE {
+            return Err(BucketError::ValueTooLong {
+                max: MAX_VALUE_SIZE,
+                got: value_bytes.len(),
+            });
+        }
+
+        let load_factor = (self.size as f64 * 0.75) as usize;
+
+        // a single item takes one bucket, a sharded one takes
+        // the index bucket plus a bucket per shard
+        let buckets_needed = if value_bytes.len() <= SINGLE_VALUE_SIZE {
+            1
+        } else {
+            1 + value_bytes.len().div_ceil(SHARD_SIZE)
+        };
+
+        if (self._no_of_taken + buckets_needed) >= load_factor {
+            self.extend();
+        }
+
+        // probe the whole chain for the key first, like `get` and
+        // `del` do, it may sit past a slot freed by a delete. The
+        // hash is taken after extending, the size might have changed
+        // buckets of the stored entry, written back if the new value
+        // can't get its shards so a failed update keeps the old one
+        let mut old_buckets: Vec<(usize, [u8; BUCKET_SIZE])> = Vec::new();
+
+        let index = match self._find_slot(key) {
+            Some(index) => {
+                let offset = index * BUCKET_SIZE;
+                let mut slots = vec![index];
+
+                if self._kvs[offset] == 3 {
+                    slots.extend(
+                        self._read_shard_indexes(offset)
+                            .into_iter()
+                            .map(|i| i as usize),
+                    );
+                }
+
+                for slot in slots {
+                    let offset = slot * BUCKET_SIZE;
+                    let bucket = self._kvs[offset..(offset + BUCKET_SIZE)]
+                        .try_into()
+                        .unwrap();
+
+                    old_buckets.push((slot, bucket));
+                }
 
-            if (bucket_index == 2 || bucket_index == 3) && saved_key == key {
                 self.del(key);
 
-                break;
+                index
             }
+            None => {
+                let home = self._get_hash_index(key);
+                let mut index = home;
+
+                // every bucket is taken, writing at `index` would
+                // overwrite whatever is stored there
+                loop {
+                    if self._kvs[index * BUCKET_SIZE] == b'\0' {
+                        break index;
+                    }
 
-            index = (index + 1) % self.size;
-        }
+                    index = (index + 1) % self.size;
+
+                    if index == home {
+                        return Err(BucketError::TableFull);
+                    }
+                }
+            }
+        };
 
         // single item bucket
-        if value_bytes.len() <= 4 {
-            let bucket = Bucket::_single_item_bucket(key, value);
+        if value_bytes.len() <= SINGLE_VALUE_SIZE {
+            let bucket = Bucket::_single_item_bucket(key.as_ref(), value);
             self._write_at_index(bucket, index);
 
-            return;
+            return Ok(());
         }
 
         let chunks = Bucket::_split_value(value_bytes);
         let indexes = self._get_empty_indexes(chunks.len(), index);
 
-        assert!(
-            chunks.len() <= 2,
-            "Value can only be parted into 2 chunks; not {}!",
-            chunks.len()
-        );
+        if indexes.len() < chunks.len() {
+            for (slot, bucket) in old_buckets {
+                self._write_at_index(bucket, slot);
+            }
+
+            return Err(BucketError::TableFull);
+        }
 
         // write an index bucket
-        let index_bucket = Bucket::_index_bucket(key, &indexes);
+        let index_bucket = Bucket::_index_bucket(key.as_ref(), &indexes);
 
         self._write_at_index(index_bucket, index);
 
@@ -202,80 +492,131 @@ impl HashTable {
         let last_index = chunks.len() - 1;
 
         for (i, chunk) in chunks.iter().enumerate() {
-            let bucket: [u8; 8];
-
-            if i == last_index {
-                bucket = Bucket::_value_bucket(1, *chunk);
+            let bucket = if i == last_index {
+                Bucket::_value_bucket(1, *chunk)
             } else {
-                bucket = Bucket::_value_bucket((i + 4) as u8, *chunk);
-            }
+                Bucket::_value_bucket((i + 4) as u8, *chunk)
+            };
 
             self._write_at_index(bucket, indexes[i] as usize);
         }
+
+        Ok(())
     }
 
-    pub fn get(&self, key: &str) -> Option<String> {
-        let mut index = self._get_hash_index(&key);
+    pub fn get<Q>(&self, key: &Q) -> Option<String>
+    where
+        K: Borrow<Q>,
+        Q: Hashable + AsRef<str> + ?Sized,
+    {
+        let index = self._find_slot(key)?;
+        let offset = index * BUCKET_SIZE;
 
-        for _ in 0..self.size {
-            let offset = index * 8;
+        if self._kvs[offset] == 2 {
+            let value_bytes = &self._kvs[(offset + 1 + KEY_SIZE)..(offset + BUCKET_SIZE)];
 
-            let index_bytes: [u8; 1] = self._kvs[offset..(offset + 1)].try_into().unwrap();
+            return Some(
+                String::from_utf8_lossy(value_bytes)
+                    .trim_end_matches('\0')
+                    .trim_start_matches('\0')
+                    .to_string(),
+            );
+        }
 
-            if index_bytes[0] == b'\0' {
-                index = (index + 1) % self.size;
-                continue;
-            }
+        let indexes = self._read_shard_indexes(offset);
 
-            let bucket_index = u8::from_le_bytes(index_bytes);
+        let mut value_vec: Vec<u8> = Vec::new();
 
-            let key_bytes = &self._kvs[(offset + 1)..(offset + 4)];
+        for i in indexes {
+            let val_bytes = self._read_value_at_index(i as usize);
 
-            let saved_key = String::from_utf8_lossy(key_bytes)
-                .trim_end_matches('\0')
-                .trim_start_matches('\0')
-                .to_string();
-
-            if bucket_index == 2 && key == saved_key {
-                let value_bytes = &self._kvs[(offset + 3)..(offset + 8)];
-
-                if key == saved_key {
-                    return Some(
-                        String::from_utf8_lossy(value_bytes)
-                            .trim_end_matches('\0')
-                            .trim_start_matches('\0')
-                            .to_string(),
-                    );
-                }
-            }
+            value_vec.append(&mut val_bytes.into());
+        }
 
-            if bucket_index == 3 && key == saved_key {
-                let index_bytes = &self._kvs[(offset + 3)..(offset + 8)];
+        let val = String::from_utf8_lossy(&value_vec)
+            .trim_end_matches('\0')
+            .trim_start_matches('\0')
+            .to_string();
 
-                let mut indexes: Vec<u16> = Vec::new();
+        Some(val)
+    }
 
-                for win in index_bytes.windows(2) {
-                    if win[0] != b'\0' {
-                        let i = u16::from_le_bytes((*win).try_into().unwrap());
+    /// Slot of the bucket holding the key and the shard slots it
+    /// points to in order, empty for a single item. Nothing is
+    /// decoded, it shows which slots `get` reads
+    pub fn trace_get<Q>(&self, key: &Q) -> Option<(usize, Vec<usize>)>
+    where
+        K: Borrow<Q>,
+        Q: Hashable + AsRef<str> + ?Sized,
+    {
+        let index = self._find_slot(key)?;
+        let offset = index * BUCKET_SIZE;
+
+        let shards = if self._kvs[offset] == 3 {
+            self._read_shard_indexes(offset)
+                .into_iter()
+                .map(|i| i as usize)
+                .collect()
+        } else {
+            Vec::new()
+        };
 
-                        indexes.push(i);
-                    }
-                }
+        Some((index, shards))
+    }
 
-                let mut value_vec: Vec<u8> = Vec::new();
+    /// Number of stored keys; only single item and index buckets
+    /// are counted, shards belong to the index bucket before them
+    pub fn len(&self) -> usize {
+        self._kvs
+            .chunks_exact(BUCKET_SIZE)
+            .filter(|bucket| bucket[0] == 2 || bucket[0] == 3)
+            .count()
+    }
 
-                for i in indexes {
-                    let val_bytes = self._read_value_at_index(i as usize);
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 
-                    value_vec.append(&mut val_bytes.try_into().unwrap());
-                }
+    /// Remove every key, the buffer keeps its current size
+    pub fn clear(&mut self) {
+        self._kvs.fill(b'\0');
+        self._no_of_taken = 0;
+    }
 
-                let val = String::from_utf8_lossy(&value_vec)
-                    .trim_end_matches('\0')
-                    .trim_start_matches('\0')
-                    .to_string();
+    /// Check if the key is stored, without reassembling a sharded value
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hashable + AsRef<str> + ?Sized,
+    {
+        self._find_slot(key).is_some()
+    }
 
-                return Some(val);
+    // slot of the single item or index bucket holding the key; an
+    // empty slot doesn't end the probe, the key could have been
+    // pushed past it
+    fn _find_slot<Q>(&self, key: &Q) -> Option<usize>
+    where
+        Q: Hashable + AsRef<str> + ?Sized,
+    {
+        let mut index = self._get_hash_index(key);
+
+        for _ in 0..self.size {
+            let offset = index * BUCKET_SIZE;
+
+            let index_bytes: [u8; 1] = self._kvs[offset..(offset + 1)].try_into().unwrap();
+
+            if index_bytes[0] == b'\0' {
+                index = (index + 1) % self.size;
+                continue;
+            }
+
+            let bucket_index = u8::from_le_bytes(index_bytes);
+
+            let saved_key = _stored_key(&self._kvs[(offset + 1)..(offset + 1 + KEY_SIZE)]);
+
+            if (bucket_index == 2 || bucket_index == 3) && key.as_ref().as_bytes() == saved_key {
+                return Some(index);
             }
 
             index = (index + 1) % self.size;
@@ -284,29 +625,32 @@ impl HashTable {
         None
     }
 
-    pub fn del(&mut self, key: &str) -> Option<String> {
-        let mut index = self._get_hash_index(&key);
+    pub fn del<Q>(&mut self, key: &Q) -> Option<String>
+    where
+        K: Borrow<Q>,
+        Q: Hashable + AsRef<str> + ?Sized,
+    {
+        let mut index = self._get_hash_index(key);
 
         for _ in 0..self.size {
-            let offset = index * 8;
+            let offset = index * BUCKET_SIZE;
 
             let index_bytes: [u8; 1] = self._kvs[offset..(offset + 1)].try_into().unwrap();
 
+            // same as `get`, an empty slot doesn't end the probe since
+            // the key could have been pushed past it
             if index_bytes[0] == b'\0' {
-                return None;
+                index = (index + 1) % self.size;
+                continue;
             }
 
             let bucket_index = u8::from_le_bytes(index_bytes);
 
-            let key_bytes = self._kvs[(offset + 1)..(offset + 4)].to_vec();
+            let saved_key = _stored_key(&self._kvs[(offset + 1)..(offset + 1 + KEY_SIZE)]);
 
-            let saved_key = String::from_utf8_lossy(&key_bytes)
-                .trim_end_matches('\0')
-                .trim_start_matches('\0')
-                .to_string();
-
-            if bucket_index == 2 && key == saved_key {
-                let value_bytes = self._kvs[(offset + 3)..(offset + 8)].to_vec();
+            if bucket_index == 2 && key.as_ref().as_bytes() == saved_key {
+                let value_bytes =
+                    self._kvs[(offset + 1 + KEY_SIZE)..(offset + BUCKET_SIZE)].to_vec();
 
                 self._del_at_index(index);
 
@@ -318,21 +662,11 @@ impl HashTable {
                 );
             }
 
-            if bucket_index == 3 && key == saved_key {
-                let index_bytes = self._kvs[(offset + 3)..(offset + 8)].to_vec();
+            if bucket_index == 3 && key.as_ref().as_bytes() == saved_key {
+                let indexes = self._read_shard_indexes(offset);
 
                 self._del_at_index(index);
 
-                let mut indexes: Vec<u16> = Vec::new();
-
-                for win in index_bytes.windows(2) {
-                    if win[0] != b'\0' {
-                        let i = u16::from_le_bytes((*win).try_into().unwrap());
-
-                        indexes.push(i);
-                    }
-                }
-
                 let mut value_vec: Vec<u8> = Vec::new();
 
                 for i in indexes {
@@ -340,7 +674,7 @@ impl HashTable {
 
                     self._del_at_index(i as usize);
 
-                    value_vec.append(&mut val_bytes.try_into().unwrap());
+                    value_vec.append(&mut val_bytes.into());
                 }
 
                 let val = String::from_utf8_lossy(&value_vec)
@@ -358,16 +692,16 @@ impl HashTable {
     }
 
     fn extend(&mut self) {
-        let new_size = self.size * 2;
-
-        let mut new_self = HashTable {
-            _kvs: vec![b'\0'; new_size * 8],
-            size: new_size,
-            _no_of_taken: 0,
+        let new_size = if self.prime_size {
+            next_prime(self.size * 2)
+        } else {
+            self.size * 2
         };
 
+        let mut new_self = Self::with_size(new_size, self.prime_size);
+
         for i in 0..self.size {
-            let offset = i * 8;
+            let offset = i * BUCKET_SIZE;
 
             let index_bytes: [u8; 1] = self._kvs[offset..(offset + 1)].try_into().unwrap();
 
@@ -378,18 +712,14 @@ impl HashTable {
             let bucket_index = u8::from_le_bytes(index_bytes);
 
             if bucket_index == 2 || bucket_index == 3 {
-                let key_bytes = &self._kvs[(offset + 1)..(offset + 4)];
+                let saved_key = _stored_key(&self._kvs[(offset + 1)..(offset + 1 + KEY_SIZE)]);
 
-                let saved_key = String::from_utf8_lossy(key_bytes)
-                    .trim_end_matches('\0')
-                    .trim_start_matches('\0')
-                    .to_string();
+                let saved_key = K::from(String::from_utf8_lossy(saved_key).to_string());
 
-                match self.get(&saved_key) {
-                    Some(val) => {
-                        new_self.set(&saved_key, &val);
-                    }
-                    None => {}
+                if let Some(val) = self.get(&saved_key) {
+                    new_self
+                        .set(&saved_key, &val)
+                        .expect("Extended table should hold every key");
                 }
 
                 continue;
@@ -400,45 +730,75 @@ impl HashTable {
     }
 
     fn _del_at_index(&mut self, index: usize) {
-        let bucket = [b'\0'; 8];
-        let offset = index * 8;
+        let bucket = [b'\0'; BUCKET_SIZE];
+        let offset = index * BUCKET_SIZE;
 
-        self._kvs[offset..(offset + 8)].copy_from_slice(&bucket);
+        self._kvs[offset..(offset + BUCKET_SIZE)].copy_from_slice(&bucket);
         self._no_of_taken -= 1;
     }
 
-    fn _write_at_index(&mut self, bucket: [u8; 8], index: usize) {
-        let offset = index * 8;
+    fn _write_at_index(&mut self, bucket: [u8; BUCKET_SIZE], index: usize) {
+        let offset = index * BUCKET_SIZE;
 
-        self._kvs[offset..(offset + 8)].copy_from_slice(&bucket);
+        self._kvs[offset..(offset + BUCKET_SIZE)].copy_from_slice(&bucket);
         self._no_of_taken += 1;
     }
 
-    fn _read_value_at_index(&self, index: usize) -> [u8; 7] {
-        let mut buffer = [b'\0'; 7];
-        let offset = index * 8;
+    fn _read_value_at_index(&self, index: usize) -> [u8; SHARD_SIZE] {
+        let mut buffer = [b'\0'; SHARD_SIZE];
+        let offset = index * BUCKET_SIZE;
 
-        buffer[0..7].copy_from_slice(&self._kvs[(offset + 1)..(offset + 8)]);
+        buffer.copy_from_slice(&self._kvs[(offset + 1)..(offset + BUCKET_SIZE)]);
 
         buffer
     }
 
-    fn _get_empty_indexes(&mut self, n: usize, index: usize) -> Vec<u16> {
+    // shard slots referenced by the index bucket at `offset`, in order;
+    // the chain ends at the shard tagged as last (1)
+    fn _read_shard_indexes(&self, offset: usize) -> Vec<u32> {
+        let mut indexes: Vec<u32> = Vec::new();
+
+        let start = offset + 1 + KEY_SIZE;
+        let end = start + 2 * SHARD_INDEX_SIZE;
+
+        for chunk in self._kvs[start..end].chunks_exact(SHARD_INDEX_SIZE) {
+            let i = u32::from_le_bytes(chunk.try_into().unwrap());
+
+            indexes.push(i);
+
+            if self._kvs[i as usize * BUCKET_SIZE] == 1 {
+                break;
+            }
+        }
+
+        indexes
+    }
+
+    // up to `n` empty slots after `index`, fewer if the table
+    // doesn't have that many left
+    fn _get_empty_indexes(&mut self, n: usize, index: usize) -> Vec<u32> {
         let mut indexes = Vec::new();
         let mut i = 0;
 
         // do not count the current index
         // it is for the index bucket
-        let mut index = index + 1;
+        let mut index = (index + 1) % self.size;
+
+        for _ in 1..self.size {
+            if i == n {
+                break;
+            }
 
-        while i < n {
-            let offset = index * 8;
-            assert!(offset + 8 <= self._kvs.len(), "Index out of bounds");
+            let offset = index * BUCKET_SIZE;
+            assert!(
+                offset + BUCKET_SIZE <= self._kvs.len(),
+                "Index out of bounds"
+            );
 
             let index_byte: [u8; 1] = self._kvs[offset..(offset + 1)].try_into().unwrap();
 
             if index_byte[0] == b'\0' {
-                indexes.push(index as u16);
+                indexes.push(u32::try_from(index).expect("Slot index should fit in a u32"));
                 i += 1;
             }
 
@@ -448,18 +808,49 @@ impl HashTable {
         indexes
     }
 
-    fn _get_hash_index(&self, key: &str) -> usize {
+    fn _get_hash_index<Q: Hashable + ?Sized>(&self, key: &Q) -> usize {
         key.hash() % self.size
     }
 
+    /// Decode the slot at `index` into a readable line, based
+    /// on its index byte
+    pub fn describe_slot(&self, index: usize) -> String {
+        let offset = index * BUCKET_SIZE;
+        assert!(
+            offset + BUCKET_SIZE <= self._kvs.len(),
+            "Index out of bounds"
+        );
+
+        let text = |bytes: &[u8]| {
+            String::from_utf8_lossy(bytes)
+                .trim_end_matches('\0')
+                .to_string()
+        };
+
+        let key = text(&self._kvs[(offset + 1)..(offset + 1 + KEY_SIZE)]);
+
+        match self._kvs[offset] {
+            0 => String::from("NULL"),
+            2 => format!(
+                "single key={key} value={}",
+                text(&self._kvs[(offset + 1 + KEY_SIZE)..(offset + BUCKET_SIZE)])
+            ),
+            3 => format!("index key={key} -> {:?}", self._read_shard_indexes(offset)),
+            _ => format!(
+                "shard data={}",
+                text(&self._kvs[(offset + 1)..(offset + BUCKET_SIZE)])
+            ),
+        }
+    }
+
     pub fn print_kvs(&self) {
-        println!("");
+        println!();
         println!("Taken: {}", self._no_of_taken);
         println!("----------------");
 
-        for i in 0..32 {
-            let offset = i * 8;
-            let buf = &self._kvs[offset..(offset + 8)];
+        for i in 0..self.size {
+            let offset = i * BUCKET_SIZE;
+            let buf = &self._kvs[offset..(offset + BUCKET_SIZE)];
 
             println!("{:?}", buf);
         }
@@ -467,3 +858,641 @@ impl HashTable {
         println!("----------------");
     }
 }
+
+// key bytes of a bucket without the `\0` padding; keys are compared
+// as bytes, decoding them first could make different keys equal
+fn _stored_key(bytes: &[u8]) -> &[u8] {
+    let len = bytes.iter().rposition(|&b| b != b'\0').map_or(0, |i| i + 1);
+
+    &bytes[..len]
+}
+
+// smallest prime which is >= n
+pub(crate) fn next_prime(n: usize) -> usize {
+    let is_prime = |n: usize| {
+        n >= 2
+            && (2..)
+                .take_while(|i| i * i <= n)
+                .all(|i| !n.is_multiple_of(i))
+    };
+
+    (n..).find(|&n| is_prime(n)).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains_key() {
+        let mut hash_table = HashTable::new();
+
+        // single item and sharded values
+        hash_table.set("k1", "v1").unwrap();
+        hash_table
+            .set("k2", "a value long enough to shard")
+            .unwrap();
+
+        assert!(hash_table.contains_key("k1"));
+        assert!(hash_table.contains_key("k2"));
+        assert!(!hash_table.contains_key("k3"));
+
+        hash_table.del("k2");
+        assert!(!hash_table.contains_key("k2"));
+    }
+
+    #[test]
+    fn test_extend_keeps_all_items() {
+        let mut hash_table = HashTable::new();
+
+        // insert 200 items, far more than the initial 32 slots;
+        // every other value is long enough to be sharded
+        for i in 0..200 {
+            let key = i.to_string();
+            let value = if i % 2 == 0 {
+                key.clone()
+            } else {
+                format!("sharded value {key}")
+            };
+
+            hash_table.set(&key, &value).unwrap();
+        }
+
+        assert!(hash_table.size > 200);
+
+        for i in 0..200 {
+            let key = i.to_string();
+            let value = if i % 2 == 0 {
+                key.clone()
+            } else {
+                format!("sharded value {key}")
+            };
+
+            assert_eq!(hash_table.get(&key), Some(value), "key {key}");
+        }
+    }
+
+    #[test]
+    fn test_del_past_empty_slot() {
+        let mut hash_table = HashTable::new();
+
+        // find two keys which land on the same home slot
+        let home = hash_table._get_hash_index("a");
+
+        let other = (0..1000)
+            .map(|i: usize| i.to_string())
+            .find(|key| hash_table._get_hash_index(key) == home)
+            .unwrap();
+
+        hash_table.set("a", "1").unwrap();
+        hash_table.set(&other, "2").unwrap();
+
+        // free the home slot, leaving a gap before the far key
+        assert_eq!(hash_table.del("a"), Some(String::from("1")));
+
+        assert_eq!(hash_table.get(&other), Some(String::from("2")));
+        assert_eq!(hash_table.del(&other), Some(String::from("2")));
+        assert_eq!(hash_table.get(&other), None);
+
+        // re-setting the far key past the gap updates it in place
+        // instead of storing a second copy in the freed home slot
+        hash_table.set("a", "1").unwrap();
+        hash_table.set(&other, "2").unwrap();
+        hash_table.del("a");
+        hash_table.set(&other, "3").unwrap();
+
+        assert_eq!(hash_table.len(), 1);
+        assert_eq!(hash_table.get(&other), Some(String::from("3")));
+        assert_eq!(hash_table.del(&other), Some(String::from("3")));
+        assert_eq!(hash_table.get(&other), None);
+    }
+
+    #[test]
+    fn test_longer_keys() {
+        let mut hash_table = HashTable::new();
+
+        hash_table.set("user:1234", "alice").unwrap();
+        hash_table
+            .set("user:5678", "a value long enough to shard")
+            .unwrap();
+        hash_table.set("sixteen_byte_key", "max").unwrap();
+
+        assert_eq!(hash_table.get("user:1234"), Some(String::from("alice")));
+        assert_eq!(
+            hash_table.get("user:5678"),
+            Some(String::from("a value long enough to shard"))
+        );
+        assert_eq!(
+            hash_table.get("sixteen_byte_key"),
+            Some(String::from("max"))
+        );
+
+        // keys past KEY_SIZE are still rejected
+        assert_eq!(
+            hash_table.set("seventeen_byte_ke", "nope"),
+            Err(BucketError::KeyTooLong { max: 16, got: 17 })
+        );
+        assert_eq!(hash_table.get("seventeen_byte_ke"), None);
+
+        assert_eq!(hash_table.del("user:1234"), Some(String::from("alice")));
+        assert_eq!(hash_table.get("user:1234"), None);
+    }
+
+    #[test]
+    fn test_len_counts_logical_items() {
+        let mut hash_table = HashTable::new();
+        assert!(hash_table.is_empty());
+
+        hash_table.set("single", "v").unwrap();
+        hash_table
+            .set("sharded", "a value long enough to shard")
+            .unwrap();
+
+        // 3 buckets are taken (single, index and shard), but there are only 2 keys
+        assert_eq!(hash_table._no_of_taken, 3);
+        assert_eq!(hash_table.len(), 2);
+
+        hash_table.del("sharded");
+        assert_eq!(hash_table.len(), 1);
+        assert!(!hash_table.is_empty());
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut hash_table = HashTable::new();
+
+        hash_table.set("single", "v").unwrap();
+        hash_table
+            .set("sharded", "a value long enough to shard")
+            .unwrap();
+
+        hash_table.clear();
+
+        assert_eq!(hash_table.len(), 0);
+        assert_eq!(hash_table._no_of_taken, 0);
+        assert_eq!(hash_table.get("single"), None);
+        assert_eq!(hash_table.get("sharded"), None);
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut hash_table = HashTable::new();
+
+        hash_table.set("single", "v").unwrap();
+        hash_table
+            .set("sharded", "a value long enough to shard")
+            .unwrap();
+
+        let mut cloned = hash_table.clone();
+
+        cloned.set("single", "changed").unwrap();
+        cloned.del("sharded");
+
+        assert_eq!(hash_table.get("single"), Some(String::from("v")));
+        assert_eq!(
+            hash_table.get("sharded"),
+            Some(String::from("a value long enough to shard"))
+        );
+        assert_eq!(hash_table.len(), 2);
+
+        assert_eq!(cloned.get("single"), Some(String::from("changed")));
+        assert_eq!(cloned.get("sharded"), None);
+        assert_eq!(cloned.len(), 1);
+    }
+
+    #[test]
+    fn test_shard_indexes_past_u16() {
+        let mut hash_table = HashTable::<String>::with_size(70_000, false);
+
+        // a key whose shards land past what a u16 can address
+        let key = (0..)
+            .map(|i: usize| i.to_string())
+            .find(|key| {
+                let home = hash_table._get_hash_index(key);
+                home > u16::MAX as usize && home + 3 < hash_table.size
+            })
+            .unwrap();
+
+        let value = "a value long enough to be split into two shards";
+        hash_table.set(&key, value).unwrap();
+
+        let home = hash_table._get_hash_index(&key);
+        let indexes = hash_table._read_shard_indexes(home * BUCKET_SIZE);
+
+        assert_eq!(indexes.len(), 2);
+        assert!(indexes.iter().all(|&i| i > u16::MAX as u32));
+
+        assert_eq!(hash_table.get(&key), Some(String::from(value)));
+        assert_eq!(hash_table.del(&key), Some(String::from(value)));
+        assert!(hash_table.is_empty());
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        let mut hash_table = HashTable::with_capacity(1000);
+        let size = hash_table.size;
+
+        for i in 0..1000 {
+            hash_table.set(&i.to_string(), "value").unwrap();
+        }
+
+        assert_eq!(hash_table.size, size);
+        assert_eq!(hash_table.len(), 1000);
+        assert_eq!(hash_table.get("999"), Some(String::from("value")));
+
+        // never below the default size
+        assert_eq!(HashTable::with_capacity(0).size, 32);
+    }
+
+    #[test]
+    fn test_multi_byte_keys() {
+        let mut hash_table = HashTable::new();
+
+        // "é" is 2 bytes as well
+        hash_table.set("é", "accent").unwrap();
+        hash_table.set("ab", "ascii").unwrap();
+
+        assert_eq!(hash_table.get("é"), Some(String::from("accent")));
+        assert_eq!(hash_table.get("ab"), Some(String::from("ascii")));
+
+        // a NUL would blur where the padding starts
+        assert_eq!(
+            hash_table.set("a\0", "nul"),
+            Err(BucketError::KeyContainsNul)
+        );
+        assert_eq!(
+            hash_table.set("\0a", "nul"),
+            Err(BucketError::KeyContainsNul)
+        );
+        assert_eq!(hash_table.get("a"), None);
+
+        hash_table.set("a", "value").unwrap();
+        assert_eq!(hash_table.get("a"), Some(String::from("value")));
+
+        // 16 bytes fit, a 17th byte is rejected instead of cut off
+        hash_table.set(&"é".repeat(8), "fits").unwrap();
+        assert!(hash_table
+            .set(&format!("a{}", "é".repeat(8)), "too long")
+            .is_err());
+        assert_eq!(hash_table.get(&"é".repeat(8)), Some(String::from("fits")));
+    }
+
+    // slots walked from the home index of the key to where it's stored
+    fn probe_length(hash_table: &HashTable, key: &str) -> usize {
+        let home = hash_table._get_hash_index(key);
+
+        (0..hash_table.size)
+            .find(|i| {
+                let offset = ((home + i) % hash_table.size) * BUCKET_SIZE;
+                let key_bytes = &hash_table._kvs[(offset + 1)..(offset + 1 + KEY_SIZE)];
+
+                hash_table._kvs[offset] == 2
+                    && String::from_utf8_lossy(key_bytes).trim_end_matches('\0') == key
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_with_prime_size() {
+        assert_eq!(next_prime(32), 37);
+        assert_eq!(next_prime(37), 37);
+        assert_eq!(next_prime(74), 79);
+
+        // keys whose hashes are all multiples of 32, under a power of
+        // two size they can only land on every 32nd slot
+        let keys: Vec<String> = (0..)
+            .map(|i: usize| i.to_string())
+            .filter(|key| key.hash() % 32 == 0)
+            .take(200)
+            .collect();
+
+        let mut prime_table = HashTable::with_prime_size();
+        let mut table = HashTable::new();
+
+        for key in &keys {
+            let size = prime_table.size;
+
+            prime_table.set(key, "value").unwrap();
+            table.set(key, "value").unwrap();
+
+            // every extend lands on a prime again
+            if prime_table.size != size {
+                assert_eq!(next_prime(prime_table.size), prime_table.size);
+            }
+        }
+
+        assert!(prime_table.size > 37);
+
+        let average = |hash_table: &HashTable| {
+            let total: usize = keys.iter().map(|key| probe_length(hash_table, key)).sum();
+
+            total as f64 / keys.len() as f64
+        };
+
+        let prime_average = average(&prime_table);
+        let average = average(&table);
+
+        // the prime modulus mixes in the low bits the power of two
+        // drops, even though the prime table runs at a higher load
+        assert!(
+            prime_average * 4.0 < average,
+            "prime table probes {prime_average} on average, power of two {average}"
+        );
+
+        for key in &keys {
+            assert_eq!(prime_table.get(key), Some(String::from("value")));
+        }
+    }
+
+    #[test]
+    fn test_set_on_full_table() {
+        let mut hash_table = HashTable::new();
+
+        // take every bucket behind the counter's back, so the load
+        // check doesn't extend the table first
+        for i in 0..hash_table.size {
+            let bucket = Bucket::_single_item_bucket(&format!("k{i}"), "v");
+            let offset = i * BUCKET_SIZE;
+
+            hash_table._kvs[offset..(offset + BUCKET_SIZE)].copy_from_slice(&bucket);
+        }
+
+        let kvs = hash_table._kvs.clone();
+
+        assert_eq!(hash_table.set("new", "value"), Err(BucketError::TableFull));
+        assert_eq!(
+            hash_table.set("sharded", "a value long enough to shard"),
+            Err(BucketError::TableFull)
+        );
+
+        // nothing got overwritten
+        assert_eq!(hash_table._kvs, kvs);
+        assert_eq!(hash_table.get("k0"), Some(String::from("v")));
+        assert_eq!(hash_table.get("new"), None);
+
+        // a sharded key with one shard, every other bucket taken
+        let mut hash_table = HashTable::new();
+        let old_value = "a value with one shard";
+
+        hash_table.set("sharded", old_value).unwrap();
+
+        for i in 0..hash_table.size {
+            let offset = i * BUCKET_SIZE;
+
+            if hash_table._kvs[offset] == b'\0' {
+                let bucket = Bucket::_single_item_bucket(&format!("k{i}"), "v");
+                hash_table._kvs[offset..(offset + BUCKET_SIZE)].copy_from_slice(&bucket);
+            }
+        }
+
+        let kvs = hash_table._kvs.clone();
+        let no_of_taken = hash_table._no_of_taken;
+
+        // two shards don't fit in the buckets the old value frees
+        assert_eq!(
+            hash_table.set("sharded", &"x".repeat(MAX_VALUE_SIZE)),
+            Err(BucketError::TableFull)
+        );
+
+        assert_eq!(hash_table._kvs, kvs);
+        assert_eq!(hash_table._no_of_taken, no_of_taken);
+        assert_eq!(hash_table.get("sharded"), Some(String::from(old_value)));
+    }
+
+    #[test]
+    fn test_set_errors() {
+        let mut hash_table = HashTable::new();
+
+        assert_eq!(
+            hash_table.set(&"k".repeat(KEY_SIZE + 1), "value"),
+            Err(BucketError::KeyTooLong {
+                max: KEY_SIZE,
+                got: KEY_SIZE + 1
+            })
+        );
+
+        // two shards is the most an index bucket points to
+        assert_eq!(
+            hash_table.set("key", &"v".repeat(MAX_VALUE_SIZE + 1)),
+            Err(BucketError::ValueTooLong {
+                max: MAX_VALUE_SIZE,
+                got: MAX_VALUE_SIZE + 1
+            })
+        );
+        assert_eq!(hash_table.set("key", &"v".repeat(MAX_VALUE_SIZE)), Ok(()));
+
+        assert_eq!(hash_table.len(), 1);
+        assert_eq!(hash_table.get("key"), Some("v".repeat(MAX_VALUE_SIZE)));
+
+        // a full table is covered by `test_set_on_full_table`
+        assert_eq!(BucketError::TableFull.to_string(), "no empty bucket left");
+    }
+
+    #[test]
+    fn test_trace_get() {
+        let mut hash_table = HashTable::new();
+
+        hash_table.set("single", "v").unwrap();
+        hash_table
+            .set("sharded", "a value long enough to be split into two shards")
+            .unwrap();
+
+        let (index, shards) = hash_table.trace_get("sharded").unwrap();
+
+        assert_eq!(shards.len(), 2);
+        assert_eq!(hash_table._kvs[index * BUCKET_SIZE], 3);
+        assert_eq!(hash_table._kvs[shards[0] * BUCKET_SIZE], 4);
+        assert_eq!(hash_table._kvs[shards[1] * BUCKET_SIZE], 1);
+
+        let (index, shards) = hash_table.trace_get("single").unwrap();
+
+        assert!(shards.is_empty());
+        assert_eq!(hash_table._kvs[index * BUCKET_SIZE], 2);
+
+        assert_eq!(hash_table.trace_get("missing"), None);
+    }
+
+    struct UserId(String);
+
+    impl Hashable for UserId {
+        // FNV-1a
+        fn hash(&self) -> usize {
+            let mut result: u64 = 0xcbf29ce484222325;
+
+            for b in self.0.bytes() {
+                result = (result ^ b as u64).wrapping_mul(0x100000001b3);
+            }
+
+            result as usize
+        }
+    }
+
+    impl AsRef<str> for UserId {
+        fn as_ref(&self) -> &str {
+            &self.0
+        }
+    }
+
+    impl From<String> for UserId {
+        fn from(id: String) -> Self {
+            UserId(id)
+        }
+    }
+
+    #[test]
+    fn test_custom_key_type() {
+        let mut hash_table = HashTable::<UserId>::default();
+
+        // enough users to extend the table
+        for i in 0..100 {
+            hash_table
+                .set(&UserId(format!("user:{i}")), &format!("name {i}"))
+                .unwrap();
+        }
+
+        let id = UserId(String::from("user:42"));
+
+        assert_eq!(hash_table._get_hash_index(&id), id.hash() % hash_table.size);
+        assert_eq!(hash_table.get(&id), Some(String::from("name 42")));
+        assert!(hash_table.contains_key(&UserId(String::from("user:99"))));
+
+        assert_eq!(hash_table.del(&id), Some(String::from("name 42")));
+        assert_eq!(hash_table.get(&id), None);
+        assert_eq!(hash_table.len(), 99);
+
+        // string keys keep working, both as &str and &String
+        let mut hash_table = HashTable::new();
+
+        hash_table.set("k1", "v1").unwrap();
+        hash_table.set(&String::from("k2"), "v2").unwrap();
+
+        assert_eq!(
+            hash_table.get(&String::from("k1")),
+            Some(String::from("v1"))
+        );
+        assert_eq!(hash_table.get("k2"), Some(String::from("v2")));
+    }
+
+    #[test]
+    fn test_describe_slot() {
+        let mut hash_table = HashTable::new();
+
+        hash_table.set("k1", "v1").unwrap();
+        hash_table
+            .set("k2", "a value long enough to shard")
+            .unwrap();
+
+        let single = hash_table._get_hash_index("k1");
+        assert_eq!(hash_table.describe_slot(single), "single key=k1 value=v1");
+
+        let index = hash_table._get_hash_index("k2");
+        let shard = hash_table._read_shard_indexes(index * BUCKET_SIZE)[0] as usize;
+
+        assert_eq!(
+            hash_table.describe_slot(index),
+            format!("index key=k2 -> [{shard}]")
+        );
+        assert_eq!(
+            hash_table.describe_slot(shard),
+            "shard data=a value long enough to shard"
+        );
+
+        let empty = (0..hash_table.size)
+            .find(|&i| hash_table._kvs[i * BUCKET_SIZE] == 0)
+            .unwrap();
+        assert_eq!(hash_table.describe_slot(empty), "NULL");
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join("file_hash_test_bucket_save_and_load.tc");
+        let _ = fs::remove_file(&path);
+
+        let mut hash_table = HashTable::with_prime_size();
+
+        for i in 0..20 {
+            hash_table.set(&i.to_string(), "value").unwrap();
+        }
+
+        hash_table
+            .set("sharded", "a value long enough to shard")
+            .unwrap();
+        hash_table.del("7");
+        hash_table.save(&path).unwrap();
+
+        let mut loaded = HashTable::<String>::load(&path).unwrap();
+
+        assert_eq!(loaded.size, hash_table.size);
+        assert_eq!(loaded._no_of_taken, hash_table._no_of_taken);
+        assert!(loaded.prime_size);
+        assert_eq!(loaded.len(), 20);
+        assert_eq!(loaded.get("7"), None);
+        assert_eq!(
+            loaded.get("sharded"),
+            Some(String::from("a value long enough to shard"))
+        );
+
+        // the shards are still found after growing the loaded table
+        for i in 20..60 {
+            loaded.set(&i.to_string(), "value").unwrap();
+        }
+
+        assert_eq!(
+            loaded.get("sharded"),
+            Some(String::from("a value long enough to shard"))
+        );
+
+        // drop the last byte of the buffer
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+
+        let err = HashTable::<String>::load(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // claim no bucket is taken
+        let mut corrupt = bytes.clone();
+        corrupt[8..16].copy_from_slice(&0_u64.to_le_bytes());
+        fs::write(&path, &corrupt).unwrap();
+
+        let err = HashTable::<String>::load(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // point the index bucket of the sharded value past the buffer
+
+        let slot = hash_table._find_slot("sharded").unwrap();
+        let start = HEADER_SIZE + slot * BUCKET_SIZE + 1 + KEY_SIZE;
+
+        let mut corrupt = bytes.clone();
+        corrupt[start..(start + SHARD_INDEX_SIZE)].copy_from_slice(&u32::MAX.to_le_bytes());
+        fs::write(&path, &corrupt).unwrap();
+
+        let err = HashTable::<String>::load(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // or at a bucket which isn't a shard
+        let single = hash_table._find_slot("0").unwrap() as u32;
+
+        let mut corrupt = bytes.clone();
+        corrupt[start..(start + SHARD_INDEX_SIZE)].copy_from_slice(&single.to_le_bytes());
+        fs::write(&path, &corrupt).unwrap();
+
+        let err = HashTable::<String>::load(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Cleanup
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut hash_table = HashTable::new();
+        assert_eq!(hash_table.capacity(), 32);
+
+        for i in 0..30 {
+            hash_table.set(&i.to_string(), "value").unwrap();
+        }
+
+        assert_eq!(hash_table.capacity(), 64);
+        assert_eq!(hash_table.len(), 30);
+    }
+}
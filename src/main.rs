@@ -3,47 +3,25 @@
 // use hash::HashTable;
 // use table::HashTable;
 
-use std::time::Instant;
-
-use hash_bucket::HashTable;
+use bench::{run_bench, KeyKind};
 
+pub mod bench;
+pub mod cache;
 pub mod hash;
 pub mod hash_bucket;
-// pub mod table;
+pub mod old;
+pub mod store;
+pub mod sync;
+pub mod table;
 
 fn main() {
-    let mut hash = HashTable::new();
-
     println!("--------------");
 
-    // ----------------------------
-    // benchmark insert operation
-    // ----------------------------
-    let start = Instant::now();
-
-    for i in 1..100 {
-        let key = i.to_string();
-        hash.set(&key, &key);
-    }
-
-    let duration = start.elapsed();
-
-    println!("Time taken for inserting 100 items: {:?}", duration);
-
-    // ----------------------------
-    // benchmark get operation
-    // ----------------------------
-    let start = Instant::now();
-
-    for i in 1..100 {
-        let key = i.to_string();
-
-        assert_eq!(hash.get(&key), Some(key.to_owned()));
-    }
-
-    let duration = start.elapsed();
+    let result = run_bench(100, KeyKind::Sequential);
 
-    println!("Time taken for retrieval of 100 items: {:?}", duration);
+    println!("Time taken for inserting 100 items: {:?}", result.insert);
+    println!("Time taken for retrieval of 100 items: {:?}", result.get);
+    println!("Time taken for deletion of 100 items: {:?}", result.del);
 }
 
 // fn main() {
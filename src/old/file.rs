@@ -6,9 +6,16 @@
 //! ## Structure
 //!
 //! - Fixed sized buckets should be stored,
-//! - 64 buckets in a file
+//! - 64 buckets in a file, another 64 are added whenever it fills up
+//!   and every entry is placed again for the new bucket count
+//! - the file starts with an 8 byte header holding the bucket count,
+//!   with the collision strategy in its top byte
 //! - fixed size keys, values can be of any size
 //! - for larger values shard across various buckets
+//! - with the `compress` feature values are run-length encoded when
+//!   that makes them smaller, flagged by the high bit of `index_indicator`
+//! - collisions are resolved by linear probing, or by Robin Hood
+//!   hashing when created with `init_with_strategy`
 //!
 //! ```rust
 //!
@@ -17,7 +24,9 @@
 //! pub struct Bucket {
 //!     index_indicator: u16, // 2 bytes; 0 (end), 1 (single bucket), 2..n (for index)
 //!     pub key: [u8; 64],    // 64 bytes
-//!     pub value: [u8; 190], // 190 bytes
+//!     value_len: u8,        // 1 byte; no of bytes used in value
+//!     probe_distance: u8,   // 1 byte; slots away from the home bucket
+//!     pub value: [u8; 188], // 188 bytes
 //! }
 //!
 //! ```
@@ -40,11 +49,39 @@ use std::fs::{File, OpenOptions};
 use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
+use crate::table::Hashable;
+
+/// Size of the header holding the bucket count (u64 LE)
+const HEADER_SIZE: usize = 8;
+
+/// The collision strategy sits in the top byte of the header
+const STRATEGY_SHIFT: u32 = 56;
+
+/// Buckets a new file starts with, and how many are appended when it's full
+const GROW_BY: usize = 64;
+
+/// Spare high bit of `index_indicator`, set when the value is compressed
+#[cfg(feature = "compress")]
+const COMPRESSED: u16 = 0x8000;
+
+/// How `add` places a key whose home bucket is taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollisionStrategy {
+    /// Take the first empty bucket after the home bucket
+    #[default]
+    Linear,
+    /// Walk like `Linear`, but hand the bucket to the incoming entry
+    /// when it's further from home than the occupant, which then moves
+    /// on instead. Probe lengths even out, so the worst case is shorter
+    RobinHood,
+}
+
 /// FileHash provides a disk-based key-value storage system with fixed-size buckets
 pub struct FileHash {
     file: File,
     num_buckets: usize,
     bucket_size: usize,
+    strategy: CollisionStrategy,
 }
 
 /// Bucket structure for storing key-value pairs
@@ -57,8 +94,82 @@ struct Bucket {
     /// Fixed-size key storage (64 bytes)
     key: [u8; 64],
 
-    /// Fixed-size value storage (190 bytes)
-    value: [u8; 190],
+    /// Length of the stored value, so values may contain zero bytes.
+    /// A value is at most 188 bytes, so one byte is enough
+    value_len: u8,
+
+    /// Buckets between this one and the home bucket of the key. Robin
+    /// Hood grows the file before it would pass 255, linear probing
+    /// never reads it and lets it saturate
+    probe_distance: u8,
+
+    /// Fixed-size value storage (188 bytes)
+    value: [u8; 188],
+}
+
+impl Bucket {
+    fn empty() -> Self {
+        Self {
+            index_indicator: 0,
+            key: [0; 64],
+            value_len: 0,
+            probe_distance: 0,
+            value: [0; 188],
+        }
+    }
+
+    /// Exactly the bytes which were stored as the value
+    fn value_bytes(&self) -> Vec<u8> {
+        // never trust the stored length past the value region
+        let len = std::cmp::min(self.value_len as usize, self.value.len());
+
+        #[cfg(feature = "compress")]
+        if self.index_indicator & COMPRESSED != 0 {
+            return rle_decompress(&self.value[..len]);
+        }
+
+        self.value[..len].to_vec()
+    }
+
+    /// Bytes to store for `value` and the indicator of its bucket
+    fn encode_value(value: &[u8]) -> (Vec<u8>, u16) {
+        #[cfg(feature = "compress")]
+        {
+            let compressed = rle_compress(value);
+
+            if compressed.len() < value.len() {
+                return (compressed, 1 | COMPRESSED);
+            }
+        }
+
+        (value.to_vec(), 1)
+    }
+}
+
+/// Run-length encode `value` as `(count, byte)` pairs
+#[cfg(feature = "compress")]
+fn rle_compress(value: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+
+    for &byte in value {
+        match compressed.len() {
+            len if len >= 2 && compressed[len - 1] == byte && compressed[len - 2] < u8::MAX => {
+                compressed[len - 2] += 1;
+            }
+            _ => compressed.extend_from_slice(&[1, byte]),
+        }
+    }
+
+    compressed
+}
+
+/// Expand the `(count, byte)` pairs written by `rle_compress`
+#[cfg(feature = "compress")]
+fn rle_decompress(compressed: &[u8]) -> Vec<u8> {
+    compressed
+        .chunks_exact(2)
+        .flat_map(|pair| std::iter::repeat_n(pair[1], pair[0] as usize))
+        .collect()
 }
 
 impl FileHash {
@@ -70,33 +181,69 @@ impl FileHash {
     /// # Returns
     /// Result with the initialized FileHash or an error
     pub fn init<P: AsRef<Path>>(path: P) -> io::Result<Self> {
-        let file = OpenOptions::new()
+        Self::init_with_strategy(path, CollisionStrategy::Linear)
+    }
+
+    /// Same as `init`, placing colliding keys with `strategy`. The
+    /// strategy is stored when the file is created, an existing file
+    /// keeps using its own whatever is passed here
+    pub fn init_with_strategy<P: AsRef<Path>>(
+        path: P,
+        strategy: CollisionStrategy,
+    ) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
+            .truncate(false)
             .open(path)?;
 
-        let num_buckets = 64;
         let bucket_size = std::mem::size_of::<Bucket>();
-
-        // Initialize file with empty buckets if it's empty
-        if file.metadata()?.len() == 0 {
-            let empty_bucket = Bucket {
-                index_indicator: 0,
-                key: [0; 64],
-                value: [0; 190],
+        let file_len = file.metadata()?.len() as usize;
+
+        // Initialize file with the header and empty buckets if it's empty
+        if file_len == 0 {
+            let mut file_hash = Self {
+                file,
+                num_buckets: 0,
+                bucket_size,
+                strategy,
             };
 
-            let mut file_handle = file.try_clone()?;
-            for _ in 0..num_buckets {
-                Self::write_bucket(&mut file_handle, &empty_bucket)?;
+            file_hash.grow()?;
+
+            return Ok(file_hash);
+        }
+
+        let mut header = [0u8; HEADER_SIZE];
+        file.read_exact(&mut header)?;
+
+        let header = u64::from_le_bytes(header);
+        let num_buckets = (header & ((1 << STRATEGY_SHIFT) - 1)) as usize;
+
+        let strategy = match header >> STRATEGY_SHIFT {
+            0 => CollisionStrategy::Linear,
+            1 => CollisionStrategy::RobinHood,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Header holds an unknown collision strategy",
+                ))
             }
+        };
+
+        if num_buckets == 0 || HEADER_SIZE + num_buckets * bucket_size != file_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Bucket count in the header doesn't match the file",
+            ));
         }
 
         Ok(Self {
             file,
             num_buckets,
             bucket_size,
+            strategy,
         })
     }
 
@@ -104,49 +251,33 @@ impl FileHash {
     ///
     /// # Arguments
     /// * `key` - Fixed-size 64-byte key
-    /// * `value` - Value to store (up to 190 bytes)
+    /// * `value` - Value to store (up to 188 bytes, after compression
+    ///   with the `compress` feature)
     ///
     /// # Returns
     /// Result indicating success or failure of the operation
     pub fn add(&mut self, key: [u8; 64], value: &[u8]) -> io::Result<()> {
+        let (value, index_indicator) = Bucket::encode_value(value);
+
         // Validate input
-        if value.len() > 190 {
+        if value.len() > 188 {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
-                "Value exceeds maximum size of 190 bytes",
+                "Value exceeds maximum size of 188 bytes",
             ));
         }
 
-        // Simple hash function (for demonstration)
-        let bucket_index = self.hash(&key);
-
-        // Try to find an empty or matching bucket
-        let mut current_index = bucket_index;
-        loop {
-            let mut current_bucket = self.read_bucket(current_index)?;
-
-            // Empty bucket found
-            if current_bucket.index_indicator == 0 {
-                current_bucket.index_indicator = 1;
-                current_bucket.key = key;
-
-                // Zero-fill and copy value
-                current_bucket.value = [0; 190];
-                current_bucket.value[..value.len()].copy_from_slice(value);
-
-                self.write_bucket_at_index(current_index, &current_bucket)?;
-                return Ok(());
-            }
-
-            // TODO: Implement more sophisticated collision handling
-            // For now, this is a basic linear probing approach
-            current_index = (current_index + 1) % self.num_buckets;
-
-            // Prevent infinite loop if no buckets are available
-            if current_index == bucket_index {
-                return Err(io::Error::new(io::ErrorKind::Other, "No available buckets"));
-            }
-        }
+        // Zero-fill and copy value
+        let mut incoming = Bucket {
+            index_indicator,
+            key,
+            value_len: value.len() as u8,
+            probe_distance: 0,
+            value: [0; 188],
+        };
+        incoming.value[..value.len()].copy_from_slice(&value);
+
+        self.place(incoming)
     }
 
     /// Retrieve a value by its key
@@ -157,31 +288,9 @@ impl FileHash {
     /// # Returns
     /// Option containing the value if found
     pub fn get(&mut self, key: &[u8; 64]) -> io::Result<Option<Vec<u8>>> {
-        let bucket_index = self.hash(key);
-        let mut current_index = bucket_index;
-
-        loop {
-            let current_bucket = self.read_bucket(current_index)?;
-
-            // Bucket matches key
-            if current_bucket.index_indicator > 0 && current_bucket.key == *key {
-                // Trim trailing zeros to get actual value
-                let value = current_bucket
-                    .value
-                    .iter()
-                    .cloned()
-                    .take_while(|&x| x != 0)
-                    .collect();
-                return Ok(Some(value));
-            }
-
-            // End of search chain
-            if current_index == bucket_index {
-                return Ok(None);
-            }
+        let found = self.find_index(key)?;
 
-            current_index = (current_index + 1) % self.num_buckets;
-        }
+        Ok(found.map(|(_, bucket)| bucket.value_bytes()))
     }
 
     /// Delete a key-value pair
@@ -192,49 +301,155 @@ impl FileHash {
     /// # Returns
     /// Option containing the deleted value if found
     pub fn delete(&mut self, key: &[u8; 64]) -> io::Result<Option<Vec<u8>>> {
-        let bucket_index = self.hash(key);
-        let mut current_index = bucket_index;
+        let Some((index, bucket)) = self.find_index(key)? else {
+            return Ok(None);
+        };
 
-        loop {
-            let mut current_bucket = self.read_bucket(current_index)?;
+        let mut hole = index;
+
+        // Robin Hood shifts the rest of the run back a bucket, so no
+        // hole is left for a lookup to stop at early
+        if self.strategy == CollisionStrategy::RobinHood {
+            loop {
+                let next = (hole + 1) % self.num_buckets;
+                let mut next_bucket = self.read_bucket(next)?;
+
+                if next_bucket.index_indicator == 0 || next_bucket.probe_distance == 0 {
+                    break;
+                }
+
+                next_bucket.probe_distance -= 1;
+                self.write_bucket_at_index(hole, &next_bucket)?;
 
-            // Bucket matches key
-            if current_bucket.index_indicator > 0 && current_bucket.key == *key {
-                // Extract value before clearing
-                let value = current_bucket
-                    .value
-                    .iter()
-                    .cloned()
-                    .take_while(|&x| x != 0)
-                    .collect();
-
-                // Reset bucket
-                current_bucket.index_indicator = 0;
-                current_bucket.key = [0; 64];
-                current_bucket.value = [0; 190];
-
-                self.write_bucket_at_index(current_index, &current_bucket)?;
-                return Ok(Some(value));
+                hole = next;
             }
+        }
+
+        self.write_bucket_at_index(hole, &Bucket::empty())?;
+
+        Ok(Some(bucket.value_bytes()))
+    }
+
+    /// Index and contents of the bucket holding `key`. Robin Hood stops
+    /// at the first empty bucket, or at one closer to its home than the
+    /// key would be, linear probing checks the whole file since deletes
+    /// leave holes in its chains
+    fn find_index(&mut self, key: &[u8; 64]) -> io::Result<Option<(usize, Bucket)>> {
+        let robin_hood = self.strategy == CollisionStrategy::RobinHood;
+        let mut current_index = self.hash(key);
+
+        for distance in 0..self.num_buckets {
+            let current_bucket = self.read_bucket(current_index)?;
 
-            // End of search chain
-            if current_index == bucket_index {
+            if current_bucket.index_indicator == 0 {
+                if robin_hood {
+                    return Ok(None);
+                }
+            } else if current_bucket.key == *key {
+                return Ok(Some((current_index, current_bucket)));
+            } else if robin_hood && (current_bucket.probe_distance as usize) < distance {
                 return Ok(None);
             }
 
             current_index = (current_index + 1) % self.num_buckets;
         }
+
+        Ok(None)
     }
 
-    /// Simple hash function to determine bucket index
+    /// Put `incoming` in the first empty bucket from its home, with
+    /// Robin Hood the entry being placed changes whenever a closer to
+    /// home occupant is evicted. The file grows if every bucket is
+    /// taken, or if a Robin Hood distance would pass 255
+    fn place(&mut self, mut incoming: Bucket) -> io::Result<()> {
+        let robin_hood = self.strategy == CollisionStrategy::RobinHood;
+
+        incoming.probe_distance = 0;
+        let mut current_index = self.hash(&incoming.key);
+
+        for _ in 0..self.num_buckets {
+            let mut current_bucket = self.read_bucket(current_index)?;
+
+            // Empty bucket found
+            if current_bucket.index_indicator == 0 {
+                return self.write_bucket_at_index(current_index, &incoming);
+            }
+
+            if robin_hood && current_bucket.probe_distance < incoming.probe_distance {
+                self.write_bucket_at_index(current_index, &incoming)?;
+                std::mem::swap(&mut incoming, &mut current_bucket);
+            }
+
+            if robin_hood && incoming.probe_distance == u8::MAX {
+                // in a sparse file the keys share a home bucket,
+                // growing wouldn't shorten the probe
+                if self.taken_buckets()?.len() * 2 < self.num_buckets {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Too many keys share a home bucket",
+                    ));
+                }
+
+                break;
+            }
+
+            incoming.probe_distance = incoming.probe_distance.saturating_add(1);
+            current_index = (current_index + 1) % self.num_buckets;
+        }
+
+        self.grow()?;
+        self.place(incoming)
+    }
+
+    /// Hash function to determine bucket index, the 32-bit hash keeps
+    /// files portable between 32 and 64-bit targets
     fn hash(&self, key: &[u8; 64]) -> usize {
-        // Basic hash: sum of key bytes modulo number of buckets
-        key.iter().map(|&x| x as usize).sum::<usize>() % self.num_buckets
+        key.as_slice().hash_u32() as usize % self.num_buckets
+    }
+
+    /// Add `GROW_BY` buckets and place every stored entry again, their
+    /// home buckets change with the count. Entries are held in memory
+    /// while the file is rewritten
+    fn grow(&mut self) -> io::Result<()> {
+        let entries = self.taken_buckets()?;
+
+        self.num_buckets += GROW_BY;
+
+        self.file.seek(SeekFrom::Start(0))?;
+
+        let header = self.num_buckets as u64 | ((self.strategy as u64) << STRATEGY_SHIFT);
+        self.file.write_all(&header.to_le_bytes())?;
+
+        // every bucket is empty again, the new ones included
+        self.file
+            .write_all(&vec![0u8; self.num_buckets * self.bucket_size])?;
+        self.file.flush()?;
+
+        for entry in entries {
+            self.place(entry)?;
+        }
+
+        Ok(())
+    }
+
+    /// Every bucket holding an entry, in file order
+    fn taken_buckets(&mut self) -> io::Result<Vec<Bucket>> {
+        let mut buckets = Vec::new();
+
+        for index in 0..self.num_buckets {
+            let bucket = self.read_bucket(index)?;
+
+            if bucket.index_indicator != 0 {
+                buckets.push(bucket);
+            }
+        }
+
+        Ok(buckets)
     }
 
     /// Read a bucket at a specific index
     fn read_bucket(&mut self, index: usize) -> io::Result<Bucket> {
-        let offset = index * self.bucket_size;
+        let offset = HEADER_SIZE + index * self.bucket_size;
         self.file.seek(SeekFrom::Start(offset as u64))?;
 
         let mut buffer = [0u8; std::mem::size_of::<Bucket>()];
@@ -243,13 +458,15 @@ impl FileHash {
         Ok(Bucket {
             index_indicator: u16::from_le_bytes([buffer[0], buffer[1]]),
             key: buffer[2..66].try_into().unwrap(),
-            value: buffer[66..256].try_into().unwrap(),
+            value_len: buffer[66],
+            probe_distance: buffer[67],
+            value: buffer[68..256].try_into().unwrap(),
         })
     }
 
     /// Write a bucket to a specific index
     fn write_bucket_at_index(&mut self, index: usize, bucket: &Bucket) -> io::Result<()> {
-        let offset = index * self.bucket_size;
+        let offset = HEADER_SIZE + index * self.bucket_size;
         self.file.seek(SeekFrom::Start(offset as u64))?;
         Self::write_bucket(&mut self.file, bucket)
     }
@@ -263,7 +480,9 @@ impl FileHash {
         let mut buffer = [0u8; std::mem::size_of::<Bucket>()];
         buffer[0..2].copy_from_slice(&indicator_bytes);
         buffer[2..66].copy_from_slice(&bucket.key);
-        buffer[66..256].copy_from_slice(&bucket.value);
+        buffer[66] = bucket.value_len;
+        buffer[67] = bucket.probe_distance;
+        buffer[68..256].copy_from_slice(&bucket.value);
 
         file.write_all(&buffer)?;
         file.flush()
@@ -303,4 +522,240 @@ mod tests {
         // Cleanup
         let _ = fs::remove_file(test_file);
     }
+
+    #[test]
+    fn test_file_grows_when_full() {
+        let test_file = std::env::temp_dir().join("file_hash_test_grow.tc");
+
+        // Cleanup any existing test file
+        let _ = fs::remove_file(&test_file);
+
+        let key = |i: usize| {
+            let mut key = [0u8; 64];
+            key[..8].copy_from_slice(&(i as u64).to_le_bytes());
+            key
+        };
+
+        let mut file_hash = FileHash::init(&test_file).expect("Failed to initialize");
+        assert_eq!(file_hash.num_buckets, 64);
+
+        // more keys than the initial 64 buckets
+        for i in 0..100 {
+            file_hash
+                .add(key(i), format!("value {i}").as_bytes())
+                .expect("Failed to add entry");
+        }
+
+        assert_eq!(file_hash.num_buckets, 128);
+
+        for i in 0..100 {
+            let retrieved = file_hash.get(&key(i)).expect("Failed to get entry");
+            assert_eq!(retrieved, Some(format!("value {i}").into_bytes()));
+        }
+
+        // the grown bucket count is read back from the header
+        drop(file_hash);
+
+        let mut file_hash = FileHash::init(&test_file).expect("Failed to reopen");
+        assert_eq!(file_hash.num_buckets, 128);
+
+        let retrieved = file_hash.get(&key(99)).expect("Failed to get entry");
+        assert_eq!(retrieved, Some(b"value 99".to_vec()));
+
+        // Cleanup
+        let _ = fs::remove_file(&test_file);
+    }
+
+    #[test]
+    fn test_value_with_zero_bytes() {
+        let test_file = std::env::temp_dir().join("file_hash_test_zero_bytes.tc");
+
+        // Cleanup any existing test file
+        let _ = fs::remove_file(&test_file);
+
+        let mut file_hash = FileHash::init(&test_file).expect("Failed to initialize");
+
+        let key = [7u8; 64];
+        file_hash.add(key, b"ab\0cd").expect("Failed to add entry");
+
+        let retrieved = file_hash.get(&key).expect("Failed to get entry");
+        assert_eq!(retrieved, Some(b"ab\0cd".to_vec()));
+
+        let deleted = file_hash.delete(&key).expect("Failed to delete entry");
+        assert_eq!(deleted, Some(b"ab\0cd".to_vec()));
+
+        // 188 bytes is the most a bucket holds now, the bytes don't
+        // repeat so the check holds with compression as well
+        let value: Vec<u8> = (0..189).map(|i| i as u8).collect();
+        assert!(file_hash.add([8u8; 64], &value).is_err());
+        assert!(file_hash.add([8u8; 64], &[1u8; 188]).is_ok());
+
+        // Cleanup
+        let _ = fs::remove_file(&test_file);
+    }
+
+    #[cfg(feature = "compress")]
+    #[test]
+    fn test_compressed_value() {
+        let test_file = std::env::temp_dir().join("file_hash_test_compress.tc");
+
+        // Cleanup any existing test file
+        let _ = fs::remove_file(&test_file);
+
+        let mut file_hash = FileHash::init(&test_file).expect("Failed to initialize");
+
+        // too long for a bucket as is, but only a few runs
+        let value = [b"a".repeat(100), b"b".repeat(90)].concat();
+        assert_eq!(value.len(), 190);
+
+        let key = [9u8; 64];
+        file_hash.add(key, &value).expect("Failed to add entry");
+
+        let bucket = file_hash.read_bucket(file_hash.hash(&key)).unwrap();
+        assert_ne!(bucket.index_indicator & COMPRESSED, 0);
+        assert_eq!(bucket.value_len, 4);
+
+        let retrieved = file_hash.get(&key).expect("Failed to get entry");
+        assert_eq!(retrieved, Some(value.clone()));
+
+        let deleted = file_hash.delete(&key).expect("Failed to delete entry");
+        assert_eq!(deleted, Some(value));
+
+        // Cleanup
+        let _ = fs::remove_file(&test_file);
+    }
+
+    #[test]
+    fn test_robin_hood_shortens_longest_probe() {
+        let linear_file = std::env::temp_dir().join("file_hash_test_linear.tc");
+        let robin_hood_file = std::env::temp_dir().join("file_hash_test_robin_hood.tc");
+
+        // Cleanup any existing test files
+        let _ = fs::remove_file(&linear_file);
+        let _ = fs::remove_file(&robin_hood_file);
+
+        let mut linear = FileHash::init(&linear_file).expect("Failed to initialize");
+        let mut robin_hood =
+            FileHash::init_with_strategy(&robin_hood_file, CollisionStrategy::RobinHood)
+                .expect("Failed to initialize");
+
+        let key = |i: usize| {
+            let mut key = [0u8; 64];
+            key[..8].copy_from_slice(&(i as u64).to_le_bytes());
+            key
+        };
+
+        // 40 keys homed on the first 8 buckets, the ones furthest along
+        // go in first so the later ones have to walk past all of them
+        let mut keys: Vec<[u8; 64]> = (0..)
+            .map(key)
+            .filter(|key| linear.hash(key) < 8)
+            .take(40)
+            .collect();
+        keys.sort_by_key(|key| std::cmp::Reverse(linear.hash(key)));
+
+        for (i, key) in keys.iter().enumerate() {
+            let value = format!("value {i}");
+
+            linear
+                .add(*key, value.as_bytes())
+                .expect("Failed to add entry");
+            robin_hood
+                .add(*key, value.as_bytes())
+                .expect("Failed to add entry");
+        }
+
+        for (i, key) in keys.iter().enumerate() {
+            let retrieved = robin_hood.get(key).expect("Failed to get entry");
+            assert_eq!(retrieved, Some(format!("value {i}").into_bytes()));
+        }
+
+        let linear_max = max_probe_distance(&mut linear);
+        let robin_hood_max = max_probe_distance(&mut robin_hood);
+
+        assert!(
+            robin_hood_max < linear_max,
+            "Robin Hood max probe {robin_hood_max} should be below linear {linear_max}"
+        );
+
+        // Cleanup
+        let _ = fs::remove_file(&linear_file);
+        let _ = fs::remove_file(&robin_hood_file);
+    }
+
+    /// Longest stored distance from a home bucket, checked against
+    /// the distance worked out from the key
+    fn max_probe_distance(file_hash: &mut FileHash) -> usize {
+        (0..file_hash.num_buckets)
+            .filter_map(|index| {
+                let bucket = file_hash.read_bucket(index).unwrap();
+
+                if bucket.index_indicator == 0 {
+                    return None;
+                }
+
+                let home = file_hash.hash(&bucket.key);
+                let distance = (index + file_hash.num_buckets - home) % file_hash.num_buckets;
+                assert_eq!(bucket.probe_distance as usize, distance);
+
+                Some(distance)
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn test_robin_hood_across_grow() {
+        let test_file = std::env::temp_dir().join("file_hash_test_robin_hood_grow.tc");
+
+        // Cleanup any existing test file
+        let _ = fs::remove_file(&test_file);
+
+        let key = |i: usize| {
+            let mut key = [0u8; 64];
+            key[..8].copy_from_slice(&(i as u64).to_le_bytes());
+            key
+        };
+
+        let mut file_hash = FileHash::init_with_strategy(&test_file, CollisionStrategy::RobinHood)
+            .expect("Failed to initialize");
+
+        // past the first 64 buckets
+        for i in 0..100 {
+            file_hash
+                .add(key(i), format!("value {i}").as_bytes())
+                .expect("Failed to add entry");
+        }
+
+        assert_eq!(file_hash.num_buckets, 128);
+
+        // every distance is from the home bucket under the new count
+        max_probe_distance(&mut file_hash);
+
+        for i in (0..100).step_by(3) {
+            let deleted = file_hash.delete(&key(i)).expect("Failed to delete entry");
+            assert_eq!(deleted, Some(format!("value {i}").into_bytes()));
+        }
+
+        // deletes shift entries back, so the distances still hold
+        max_probe_distance(&mut file_hash);
+
+        // reopened without a strategy, the stored one is used
+        drop(file_hash);
+
+        let mut file_hash = FileHash::init(&test_file).expect("Failed to reopen");
+        assert_eq!(file_hash.strategy, CollisionStrategy::RobinHood);
+
+        for i in 0..100 {
+            let expected = (i % 3 != 0).then(|| format!("value {i}").into_bytes());
+            let retrieved = file_hash.get(&key(i)).expect("Failed to get entry");
+
+            assert_eq!(retrieved, expected);
+        }
+
+        assert_eq!(file_hash.get(&key(1000)).expect("Failed to get"), None);
+
+        // Cleanup
+        let _ = fs::remove_file(&test_file);
+    }
 }
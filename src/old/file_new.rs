@@ -1,52 +1,83 @@
 use std::{
     fs::{File, OpenOptions},
-    hash::{DefaultHasher, Hash, Hasher},
-    io::{Read, Seek, SeekFrom, Write},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::Path,
 };
 
+use crate::table::Hashable;
+
 const FILE_PATH: &str = "hash.tc";
 const BUCKETS_COUNT: u64 = 32;
-const BUCKET_SIZE: usize = 128;
 const KEY_SIZE: usize = 16;
-const VALUE_SIZE: usize = 110;
+const VALUE_SIZE: usize = 256;
 const INDEX_SIZE: usize = 2;
 
-pub struct FileHash {
+// values up to this many bytes go to the small pool, longer ones
+// to the large pool whose buckets hold `VAL` bytes
+const SMALL_VALUE_SIZE: usize = 32;
+
+// the count, then the start offset and bucket count of the small
+// and of the large pool, all as little endian u64s
+const HEADER_SIZE: u64 = 5 * 8;
+
+/// On-disk table whose buckets hold keys of up to `KEY` bytes and
+/// values of up to `VAL` bytes, `FileHash` alone is the default
+/// layout with 16 byte keys and 256 byte values. Values of up to 32
+/// bytes go to a pool of smaller buckets so they don't waste a large one
+pub struct FileHash<const KEY: usize = KEY_SIZE, const VAL: usize = VALUE_SIZE> {
     file: File,
 }
 
-// 128 (2 + 16 + 110) bytes
-struct Bucket {
+/// Lines `load_from_reader` wrote, and the ones it couldn't
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadSummary {
+    pub loaded: usize,
+    /// Malformed lines, keys or values which don't fit, and lines
+    /// which found no empty bucket left
+    pub skipped: usize,
+}
+
+// the set of buckets an entry is stored in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pool {
+    Small,
+    Large,
+}
+
+// INDEX_SIZE + KEY + VAL bytes
+struct Bucket<const KEY: usize, const VAL: usize> {
     // index: [u8; INDEX_SIZE],
-    // key: [u8; KEY_SIZE],
-    // value: [u8; VALUE_SIZE],
+    // key: [u8; KEY],
+    // value: [u8; VAL],
 }
 
-impl Bucket {
-    fn new(key: &str, value: &str, index: u16) -> [u8; 128] {
+impl<const KEY: usize, const VAL: usize> Bucket<KEY, VAL> {
+    const SIZE: usize = INDEX_SIZE + KEY + VAL;
+
+    fn to_bytes(key: &str, value: &str, index: u16) -> Vec<u8> {
         let mut key_bytes = Vec::from(key.as_bytes());
         let mut value_bytes = Vec::from(value.as_bytes());
         let index = index.to_le_bytes();
 
-        key_bytes.resize(32, b'\0');
-        value_bytes.resize(94, b'\0');
+        key_bytes.resize(KEY, b'\0');
+        value_bytes.resize(VAL, b'\0');
 
-        let mut buffer = [b'\0'; 128];
+        let mut buffer = vec![b'\0'; Self::SIZE];
 
         buffer[0..INDEX_SIZE].copy_from_slice(&index);
-        buffer[INDEX_SIZE..(INDEX_SIZE + KEY_SIZE)].copy_from_slice(&key_bytes);
-        buffer[(INDEX_SIZE + KEY_SIZE)..(BUCKET_SIZE)].copy_from_slice(&value_bytes);
+        buffer[INDEX_SIZE..(INDEX_SIZE + KEY)].copy_from_slice(&key_bytes);
+        buffer[(INDEX_SIZE + KEY)..(Self::SIZE)].copy_from_slice(&value_bytes);
 
         buffer
     }
 
-    // fn from_bytes(bytes: [u8; BUCKET_SIZE]) -> Self {
+    // fn from_bytes(bytes: [u8; Self::SIZE]) -> Self {
     //     Self {
     //         index: bytes[0..INDEX_SIZE].try_into().unwrap(),
-    //         key: bytes[INDEX_SIZE..(INDEX_SIZE + KEY_SIZE)]
+    //         key: bytes[INDEX_SIZE..(INDEX_SIZE + KEY)]
     //             .try_into()
     //             .unwrap(),
-    //         value: bytes[(INDEX_SIZE + KEY_SIZE)..(BUCKET_SIZE)]
+    //         value: bytes[(INDEX_SIZE + KEY)..(Self::SIZE)]
     //             .try_into()
     //             .unwrap(),
     //     }
@@ -56,13 +87,13 @@ impl Bucket {
         u16::from_le_bytes(bytes)
     }
 
-    fn get_key_from_bytes(bytes: [u8; KEY_SIZE]) -> String {
+    fn get_key_from_bytes(bytes: [u8; KEY]) -> String {
         String::from_utf8_lossy(&bytes)
             .trim_end_matches('\0')
             .to_string()
     }
 
-    fn get_value_from_bytes(bytes: [u8; VALUE_SIZE]) -> String {
+    fn get_value_from_bytes(bytes: [u8; VAL]) -> String {
         String::from_utf8_lossy(&bytes)
             .trim_end_matches('\0')
             .to_string()
@@ -71,67 +102,228 @@ impl Bucket {
 
 impl FileHash {
     pub fn init() -> Self {
+        Self::init_at(FILE_PATH).expect("Unable to open the file")
+    }
+
+    /// Open the table with the default layout stored at `path`
+    pub fn init_at<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::open_at(path)
+    }
+}
+
+impl<const KEY: usize, const VAL: usize> FileHash<KEY, VAL> {
+    // (start offset, bucket count) of the small and the large pool
+    const POOLS: [(u64, u64); 2] = [
+        (HEADER_SIZE, BUCKETS_COUNT),
+        (
+            HEADER_SIZE + BUCKETS_COUNT * Bucket::<KEY, SMALL_VALUE_SIZE>::SIZE as u64,
+            BUCKETS_COUNT,
+        ),
+    ];
+
+    const FILE_SIZE: u64 = Self::POOLS[1].0 + BUCKETS_COUNT * Bucket::<KEY, VAL>::SIZE as u64;
+
+    /// Open the table stored at `path`, an empty file is set up with
+    /// the header and the empty buckets of both pools first. A file
+    /// whose header holds other pool ranges is `InvalidData`
+    pub fn open_at<P: AsRef<Path>>(path: P) -> io::Result<Self> {
         let mut file = OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
-            .open(FILE_PATH)
-            .expect("Unable to open the file");
+            .truncate(false)
+            .open(path)?;
 
-        if file.metadata().expect("Expected metadata").len() == 0 {
-            let count = (0 as u64).to_be_bytes();
+        let ranges = Self::POOLS
+            .iter()
+            .flat_map(|&(start, count)| [start, count]);
 
-            file.write_all(&count).expect("Unable to write count");
+        if file.metadata()?.len() == 0 {
+            file.write_all(&0_u64.to_le_bytes())?;
 
-            let buffer = [b'\0'; BUCKET_SIZE];
+            for field in ranges {
+                file.write_all(&field.to_le_bytes())?;
+            }
 
-            for _ in 0..BUCKETS_COUNT {
-                file.write_all(&buffer)
-                    .expect("Unable to write empty bucket");
+            for pool in [Pool::Small, Pool::Large] {
+                let buffer = vec![b'\0'; Self::bucket_size(pool)];
+
+                for _ in 0..BUCKETS_COUNT {
+                    file.write_all(&buffer)?;
+                }
             }
+
+            return Ok(Self { file });
+        }
+
+        let mut header = [b'\0'; HEADER_SIZE as usize];
+
+        if file.metadata()?.len() == Self::FILE_SIZE {
+            file.read_exact(&mut header)?;
         }
 
-        Self { file }
+        // the count comes first, the pool ranges follow it
+        let stored = header[8..]
+            .chunks_exact(8)
+            .map(|field| u64::from_le_bytes(field.try_into().unwrap()));
+
+        if !stored.eq(ranges) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Pool ranges in the header don't match the table layout",
+            ));
+        }
+
+        Ok(Self { file })
     }
 
-    pub fn write(&mut self, key: &str, value: &str) {
-        if key.len() > KEY_SIZE {
-            eprintln!("[ERR] Key size should be less then {KEY_SIZE}");
-            return;
+    /// Insert or update a key, `false` if nothing was written because
+    /// the key or value doesn't fit or no empty bucket is left
+    pub fn write(&mut self, key: &str, value: &str) -> bool {
+        if key.len() > KEY {
+            eprintln!("[ERR] Key size should be less then {KEY}");
+            return false;
         }
 
-        let mut index = Self::hash(key);
+        if value.len() > VAL {
+            eprintln!("[ERR] Value size should be less then {VAL}");
 
-        if value.len() > VALUE_SIZE {
-            eprintln!("[ERR] Value size should be less then {VALUE_SIZE}");
+            return false;
+        }
+
+        // a short value falls back to the large pool once the small
+        // one is full
+        let pools: &[Pool] = if value.len() <= SMALL_VALUE_SIZE {
+            &[Pool::Small, Pool::Large]
+        } else {
+            &[Pool::Large]
+        };
 
-            return;
+        let existing = self.find_index(key);
+
+        // update the value for the same key
+        if let Some((old_pool, index)) = existing {
+            if pools.contains(&old_pool) {
+                self.write_bucket_at_offset(old_pool, index, key, value);
+
+                return true;
+            }
         }
 
-        loop {
-            let bucket_index = self.read_index_at_offset(index);
+        for &pool in pools {
+            let mut index = Self::hash(key);
 
-            match bucket_index {
-                Some(_) => {
-                    // update the value for the same key
-                    index = (index + 1) % BUCKETS_COUNT;
+            for _ in 0..BUCKETS_COUNT {
+                if self.read_index_at_offset(pool, index).is_none() {
+                    self.write_bucket_at_offset(pool, index, key, value);
+
+                    // a value which changed pools leaves its old bucket,
+                    // the key is still counted once
+                    match existing {
+                        Some((old_pool, old_index)) => {
+                            self.clear_bucket_at_offset(old_pool, old_index)
+                        }
+                        None => self.update_count(1),
+                    }
+
+                    return true;
                 }
-                None => {
-                    break;
+
+                index = (index + 1) % BUCKETS_COUNT;
+            }
+        }
+
+        eprintln!("[ERR] No empty bucket left for {key}");
+
+        false
+    }
+
+    pub fn read(&mut self, key: &str) -> Option<String> {
+        let (pool, index) = self.find_index(key)?;
+
+        Some(self.read_value_at_offset(pool, index))
+    }
+
+    pub fn delete(&mut self, key: &str) -> Option<String> {
+        let (pool, index) = self.find_index(key)?;
+
+        let value = self.read_value_at_offset(pool, index);
+
+        self.clear_bucket_at_offset(pool, index);
+        self.update_count(-1);
+
+        Some(value)
+    }
+
+    /// Write every `key\tvalue` line of `reader`, handing back how
+    /// many were written. Lines without a tab, which aren't UTF-8,
+    /// whose key or value doesn't fit or which found no empty bucket are
+    /// skipped and counted instead
+    pub fn load_from_reader<R: Read>(&mut self, reader: R) -> io::Result<LoadSummary> {
+        let mut loaded = 0;
+        let mut skipped = 0;
+
+        for line in BufReader::new(reader).split(b'\n') {
+            let line = line?;
+
+            // blank lines are allowed, not malformed
+            if line.is_empty() {
+                continue;
+            }
+
+            let pair = std::str::from_utf8(&line)
+                .ok()
+                .and_then(|line| line.trim_end_matches('\r').split_once('\t'));
+
+            match pair {
+                Some((key, value))
+                    if !key.is_empty()
+                        && key.len() <= KEY
+                        && value.len() <= VAL
+                        && self.write(key, value) =>
+                {
+                    loaded += 1;
                 }
+                _ => skipped += 1,
             }
         }
 
-        let bucket = Bucket::new(key, value, 1);
+        Ok(LoadSummary { loaded, skipped })
+    }
 
-        self.file
-            .write_all(&bucket)
-            .expect("Unable to write bucket");
+    /// Every stored key and value, the small pool first and each
+    /// pool in bucket order
+    pub fn entries(&mut self) -> io::Result<Vec<(String, String)>> {
+        let mut entries = Vec::new();
+
+        for pool in [Pool::Small, Pool::Large] {
+            self.file.seek(SeekFrom::Start(Self::offset(pool, 0)))?;
+
+            let bucket_size = Self::bucket_size(pool);
+            let mut buffer = vec![b'\0'; bucket_size];
+
+            for _ in 0..BUCKETS_COUNT {
+                self.file.read_exact(&mut buffer)?;
+
+                // empty bucket
+                if buffer[0] == b'\0' {
+                    continue;
+                }
 
-        self.update_count();
+                let key = Bucket::<KEY, VAL>::get_key_from_bytes(
+                    buffer[INDEX_SIZE..(INDEX_SIZE + KEY)].try_into().unwrap(),
+                );
+                let value = Self::value_from_bytes(pool, &buffer[(INDEX_SIZE + KEY)..bucket_size]);
+
+                entries.push((key, value));
+            }
+        }
+
+        Ok(entries)
     }
 
-    fn update_count(&mut self) {
+    /// Number of stored keys, as kept in the count header
+    pub fn count(&mut self) -> u64 {
         self.file.seek(SeekFrom::Start(0)).expect("Unable to seek");
 
         let mut buf = [b'\0'; 8];
@@ -140,7 +332,56 @@ impl FileHash {
             .read_exact(&mut buf)
             .expect("Unable to read count");
 
-        let count = u64::from_be_bytes(buf) + 1;
+        u64::from_le_bytes(buf)
+    }
+
+    // Probe from the home slot once around each pool. An empty slot
+    // doesn't end the probe, a delete might have left a hole in the chain.
+    fn find_index(&mut self, key: &str) -> Option<(Pool, u64)> {
+        for pool in [Pool::Small, Pool::Large] {
+            let mut index = Self::hash(key);
+
+            for _ in 0..BUCKETS_COUNT {
+                if self.read_index_at_offset(pool, index).is_some()
+                    && self.read_key_at_offset(pool, index) == key
+                {
+                    return Some((pool, index));
+                }
+
+                index = (index + 1) % BUCKETS_COUNT;
+            }
+        }
+
+        None
+    }
+
+    fn write_bucket_at_offset(&mut self, pool: Pool, index: u64, key: &str, value: &str) {
+        let bucket = match pool {
+            Pool::Small => Bucket::<KEY, SMALL_VALUE_SIZE>::to_bytes(key, value, 1),
+            Pool::Large => Bucket::<KEY, VAL>::to_bytes(key, value, 1),
+        };
+
+        self.file
+            .seek(SeekFrom::Start(Self::offset(pool, index)))
+            .expect("Unable to seek");
+
+        self.file
+            .write_all(&bucket)
+            .expect("Unable to write bucket");
+    }
+
+    fn clear_bucket_at_offset(&mut self, pool: Pool, index: u64) {
+        self.file
+            .seek(SeekFrom::Start(Self::offset(pool, index)))
+            .expect("Unable to seek");
+
+        self.file
+            .write_all(&vec![b'\0'; Self::bucket_size(pool)])
+            .expect("Unable to clear bucket");
+    }
+
+    fn update_count(&mut self, delta: i64) {
+        let count = self.count().saturating_add_signed(delta);
 
         self.file.seek(SeekFrom::Start(0)).expect("Unable to seek");
 
@@ -149,9 +390,9 @@ impl FileHash {
             .expect("Unable to write count");
     }
 
-    fn read_index_at_offset(&mut self, index: u64) -> Option<u16> {
+    fn read_index_at_offset(&mut self, pool: Pool, index: u64) -> Option<u16> {
         self.file
-            .seek(SeekFrom::Start(index * (BUCKET_SIZE as u64) + 8))
+            .seek(SeekFrom::Start(Self::offset(pool, index)))
             .expect("Unable to seek");
 
         let mut buffer = [b'\0'; 2];
@@ -159,19 +400,370 @@ impl FileHash {
         self.file.read_exact(&mut buffer).expect("Unable to read");
 
         if buffer[0] != b'\0' {
-            Some(Bucket::get_index_from_bytes(buffer))
+            Some(Bucket::<KEY, VAL>::get_index_from_bytes(buffer))
         } else {
             None
         }
     }
 
+    fn read_key_at_offset(&mut self, pool: Pool, index: u64) -> String {
+        self.file
+            .seek(SeekFrom::Start(
+                Self::offset(pool, index) + INDEX_SIZE as u64,
+            ))
+            .expect("Unable to seek");
+
+        let mut buffer = [b'\0'; KEY];
+
+        self.file.read_exact(&mut buffer).expect("Unable to read");
+
+        Bucket::<KEY, VAL>::get_key_from_bytes(buffer)
+    }
+
+    fn read_value_at_offset(&mut self, pool: Pool, index: u64) -> String {
+        self.file
+            .seek(SeekFrom::Start(
+                Self::offset(pool, index) + (INDEX_SIZE + KEY) as u64,
+            ))
+            .expect("Unable to seek");
+
+        let mut buffer = vec![b'\0'; Self::bucket_size(pool) - INDEX_SIZE - KEY];
+
+        self.file.read_exact(&mut buffer).expect("Unable to read");
+
+        Self::value_from_bytes(pool, &buffer)
+    }
+
+    fn value_from_bytes(pool: Pool, bytes: &[u8]) -> String {
+        match pool {
+            Pool::Small => {
+                Bucket::<KEY, SMALL_VALUE_SIZE>::get_value_from_bytes(bytes.try_into().unwrap())
+            }
+            Pool::Large => Bucket::<KEY, VAL>::get_value_from_bytes(bytes.try_into().unwrap()),
+        }
+    }
+
+    fn bucket_size(pool: Pool) -> usize {
+        match pool {
+            Pool::Small => Bucket::<KEY, SMALL_VALUE_SIZE>::SIZE,
+            Pool::Large => Bucket::<KEY, VAL>::SIZE,
+        }
+    }
+
+    // position of the bucket in the file
+    fn offset(pool: Pool, index: u64) -> u64 {
+        Self::POOLS[pool as usize].0 + index * Self::bucket_size(pool) as u64
+    }
+
+    // the 32-bit hash is the same on every target, so files
+    // written on one can be read on another
     fn hash(key: &str) -> u64 {
-        let mut hasher = DefaultHasher::new();
+        key.as_bytes().hash_u32() as u64 % BUCKETS_COUNT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::sync::Mutex;
+
+    // every test goes through the same FILE_PATH
+    static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn test_write_same_key_twice() {
+        let _lock = FILE_LOCK.lock().unwrap();
+
+        // Cleanup any existing file
+        let _ = fs::remove_file(FILE_PATH);
+
+        let mut file_hash = FileHash::init();
+
+        file_hash.write("key", "first");
+        file_hash.write("key", "second");
+        file_hash.write("other", "value");
+
+        let occupied = [Pool::Small, Pool::Large]
+            .into_iter()
+            .flat_map(|pool| (0..BUCKETS_COUNT).map(move |index| (pool, index)))
+            .filter(|&(pool, index)| file_hash.read_index_at_offset(pool, index).is_some())
+            .count();
+
+        assert_eq!(occupied, 2);
+
+        // Cleanup
+        let _ = fs::remove_file(FILE_PATH);
+    }
+
+    #[test]
+    fn test_write_read_delete() {
+        let _lock = FILE_LOCK.lock().unwrap();
+
+        // Cleanup any existing file
+        let _ = fs::remove_file(FILE_PATH);
+
+        let mut file_hash = FileHash::init();
+
+        file_hash.write("key", "value");
+        file_hash.write("other", "other value");
+
+        assert_eq!(file_hash.read("key"), Some(String::from("value")));
+        assert_eq!(file_hash.read("missing"), None);
+
+        assert_eq!(file_hash.delete("key"), Some(String::from("value")));
+        assert_eq!(file_hash.read("key"), None);
+        assert_eq!(file_hash.delete("key"), None);
+
+        assert_eq!(file_hash.read("other"), Some(String::from("other value")));
+
+        // Cleanup
+        let _ = fs::remove_file(FILE_PATH);
+    }
+
+    #[test]
+    fn test_read_after_delete_in_chain() {
+        let _lock = FILE_LOCK.lock().unwrap();
+
+        // Cleanup any existing file
+        let _ = fs::remove_file(FILE_PATH);
+
+        let mut file_hash = FileHash::init();
+
+        // find two keys which land on the same bucket
+        let home = <FileHash>::hash("0");
+        let other = (1..)
+            .map(|i: usize| i.to_string())
+            .find(|key| <FileHash>::hash(key) == home)
+            .unwrap();
+
+        file_hash.write("0", "first");
+        file_hash.write(&other, "second");
+
+        assert_eq!(file_hash.delete("0"), Some(String::from("first")));
+        assert_eq!(file_hash.read(&other), Some(String::from("second")));
+
+        // Cleanup
+        let _ = fs::remove_file(FILE_PATH);
+    }
+
+    #[test]
+    fn test_count() {
+        let _lock = FILE_LOCK.lock().unwrap();
+
+        // Cleanup any existing file
+        let _ = fs::remove_file(FILE_PATH);
+
+        let mut file_hash = FileHash::init();
+
+        assert_eq!(file_hash.count(), 0);
+
+        file_hash.write("one", "1");
+        file_hash.write("two", "2");
+        file_hash.write("three", "3");
+
+        assert_eq!(file_hash.count(), 3);
+
+        // overwriting doesn't add a key
+        file_hash.write("one", "uno");
+        assert_eq!(file_hash.count(), 3);
+
+        file_hash.delete("two");
+        assert_eq!(file_hash.count(), 2);
+
+        // the count survives reopening the file
+        drop(file_hash);
+        assert_eq!(FileHash::init().count(), 2);
+
+        // Cleanup
+        let _ = fs::remove_file(FILE_PATH);
+    }
+
+    #[test]
+    fn test_init_at() {
+        let first_path = std::env::temp_dir().join("file_hash_test_init_at_first.tc");
+        let second_path = std::env::temp_dir().join("file_hash_test_init_at_second.tc");
+
+        // Cleanup any existing files
+        let _ = fs::remove_file(&first_path);
+        let _ = fs::remove_file(&second_path);
+
+        let mut first = FileHash::init_at(&first_path).unwrap();
+        let mut second = FileHash::init_at(&second_path).unwrap();
+
+        first.write("key", "first");
+        second.write("key", "second");
+        second.write("other", "value");
+
+        assert_eq!(first.read("key"), Some(String::from("first")));
+        assert_eq!(first.read("other"), None);
+        assert_eq!(second.read("key"), Some(String::from("second")));
+
+        assert_eq!(first.count(), 1);
+        assert_eq!(second.count(), 2);
+
+        // reopening keeps what was written
+        drop(first);
+        let mut first = FileHash::init_at(&first_path).unwrap();
+        assert_eq!(first.read("key"), Some(String::from("first")));
+
+        // Cleanup
+        let _ = fs::remove_file(&first_path);
+        let _ = fs::remove_file(&second_path);
+    }
+
+    #[test]
+    fn test_entries() {
+        let path = std::env::temp_dir().join("file_hash_test_entries.tc");
+
+        // Cleanup any existing file
+        let _ = fs::remove_file(&path);
+
+        let mut file_hash = FileHash::init_at(&path).unwrap();
+
+        for i in 0..5 {
+            file_hash.write(&format!("key_{i}"), &format!("value_{i}"));
+        }
+
+        file_hash.write("deleted", "value");
+        file_hash.delete("deleted");
+
+        let mut entries = file_hash.entries().unwrap();
+        entries.sort();
+
+        let expected: Vec<_> = (0..5)
+            .map(|i| (format!("key_{i}"), format!("value_{i}")))
+            .collect();
+
+        assert_eq!(entries, expected);
+
+        // Cleanup
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_custom_layout() {
+        let path = std::env::temp_dir().join("file_hash_test_custom_layout.tc");
+
+        // Cleanup any existing file
+        let _ = fs::remove_file(&path);
+
+        let mut file_hash = FileHash::<32, 64>::open_at(&path).unwrap();
+
+        // too long for the default 16 byte keys
+        let key = "k".repeat(30);
+
+        file_hash.write(&key, "value");
+        file_hash.write("short", &"v".repeat(64));
+
+        assert_eq!(file_hash.read(&key), Some(String::from("value")));
+        assert_eq!(file_hash.read("short"), Some("v".repeat(64)));
+        assert_eq!(file_hash.count(), 2);
+
+        // 40 byte header, 32 small buckets of 2 + 32 + 32 bytes and
+        // 32 large ones of 2 + 32 + 64 bytes
+        let len = fs::metadata(&path).unwrap().len();
+        assert_eq!(len, 40 + 32 * 66 + 32 * 98);
+
+        // Cleanup
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_load_from_reader() {
+        let path = std::env::temp_dir().join("file_hash_test_load_from_reader.tc");
+
+        // Cleanup any existing file
+        let _ = fs::remove_file(&path);
+
+        let mut file_hash = FileHash::init_at(&path).unwrap();
+
+        let mut input: String = (0..10).map(|i| format!("key_{i}\tvalue_{i}\n")).collect();
+
+        // skipped, not loaded
+        input.push_str("no tab here\n");
+        input.push_str(&format!("{}\tvalue\n", "k".repeat(KEY_SIZE + 1)));
+
+        let summary = file_hash.load_from_reader(input.as_bytes()).unwrap();
+
+        assert_eq!(
+            summary,
+            LoadSummary {
+                loaded: 10,
+                skipped: 2
+            }
+        );
+        assert_eq!(file_hash.count(), 10);
+        assert_eq!(file_hash.read("key_9"), Some(String::from("value_9")));
+
+        // more distinct keys than the small pool has buckets, the
+        // rest of them go to the large pool
+        let input: String = (0..40).map(|i| format!("more_{i}\tvalue\n")).collect();
+
+        let summary = file_hash.load_from_reader(input.as_bytes()).unwrap();
+
+        assert_eq!(summary.loaded, 40);
+        assert_eq!(summary.skipped, 0);
+        assert_eq!(file_hash.count(), 50);
+        assert_eq!(file_hash.read("more_39"), Some(String::from("value")));
+
+        // both pools are full now
+        let input: String = (0..20).map(|i| format!("last_{i}\tvalue\n")).collect();
+
+        let summary = file_hash.load_from_reader(input.as_bytes()).unwrap();
+
+        assert_eq!(summary.loaded, 14);
+        assert_eq!(summary.skipped, 6);
+        assert_eq!(file_hash.count(), 64);
+
+        // Cleanup
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_value_pools() {
+        let path = std::env::temp_dir().join("file_hash_test_value_pools.tc");
+
+        // Cleanup any existing file
+        let _ = fs::remove_file(&path);
+
+        let mut file_hash = FileHash::init_at(&path).unwrap();
+
+        let short = "v".repeat(10);
+        let long = "v".repeat(200);
+
+        file_hash.write("short", &short);
+        file_hash.write("long", &long);
+
+        assert_eq!(file_hash.find_index("short").unwrap().0, Pool::Small);
+        assert_eq!(file_hash.find_index("long").unwrap().0, Pool::Large);
+
+        assert_eq!(file_hash.read("short"), Some(short.clone()));
+        assert_eq!(file_hash.read("long"), Some(long.clone()));
+
+        // a longer value moves the key over to the large pool
+        file_hash.write("short", &long);
+
+        assert_eq!(file_hash.find_index("short").unwrap().0, Pool::Large);
+        assert_eq!(file_hash.read("short"), Some(long.clone()));
+        assert_eq!(file_hash.entries().unwrap().len(), 2);
+        assert_eq!(file_hash.count(), 2);
+
+        assert_eq!(file_hash.delete("long"), Some(long));
+        assert_eq!(file_hash.read("long"), None);
+
+        // 40 byte header, 32 small buckets of 2 + 16 + 32 bytes and
+        // 32 large ones of 2 + 16 + 256 bytes
+        let len = fs::metadata(&path).unwrap().len();
+        assert_eq!(len, 40 + 32 * 50 + 32 * 274);
 
-        key.hash(&mut hasher);
+        // a file with other pool ranges isn't opened
+        drop(file_hash);
 
-        let val = hasher.finish();
+        let err = FileHash::<16, 64>::open_at(&path).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
 
-        return val % BUCKETS_COUNT;
+        // Cleanup
+        let _ = fs::remove_file(&path);
     }
 }
@@ -0,0 +1,132 @@
+use crate::{hash, hash_bucket, table};
+
+/// Common API of the string `HashTable`s, so code can swap one
+/// implementation for another
+pub trait KeyValueStore {
+    /// Insert or update a key, `false` if the table couldn't store it
+    fn set(&mut self, key: &str, value: &str) -> bool;
+
+    fn get(&self, key: &str) -> Option<String>;
+
+    fn del(&mut self, key: &str) -> Option<String>;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn contains_key(&self, key: &str) -> bool;
+}
+
+impl KeyValueStore for hash::HashTable {
+    fn set(&mut self, key: &str, value: &str) -> bool {
+        hash::HashTable::set(self, key, value).is_ok()
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        hash::HashTable::get(self, key)
+    }
+
+    fn del(&mut self, key: &str) -> Option<String> {
+        hash::HashTable::del(self, key)
+    }
+
+    fn len(&self) -> usize {
+        hash::HashTable::len(self)
+    }
+
+    fn contains_key(&self, key: &str) -> bool {
+        hash::HashTable::contains_key(self, key)
+    }
+}
+
+impl KeyValueStore for hash_bucket::HashTable {
+    fn set(&mut self, key: &str, value: &str) -> bool {
+        hash_bucket::HashTable::set(self, key, value).is_ok()
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        hash_bucket::HashTable::get(self, key)
+    }
+
+    fn del(&mut self, key: &str) -> Option<String> {
+        hash_bucket::HashTable::del(self, key)
+    }
+
+    fn len(&self) -> usize {
+        hash_bucket::HashTable::len(self)
+    }
+
+    fn contains_key(&self, key: &str) -> bool {
+        hash_bucket::HashTable::contains_key(self, key)
+    }
+}
+
+impl KeyValueStore for table::HashTable<String, String> {
+    fn set(&mut self, key: &str, value: &str) -> bool {
+        self.insert(key.to_string(), value.to_string());
+
+        true
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        table::HashTable::get(self, &key.to_string()).cloned()
+    }
+
+    fn del(&mut self, key: &str) -> Option<String> {
+        self.remove(&key.to_string())
+    }
+
+    fn len(&self) -> usize {
+        table::HashTable::len(self)
+    }
+
+    fn contains_key(&self, key: &str) -> bool {
+        table::HashTable::contains_key(self, &key.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // same checks for every implementation
+    fn exercise(mut store: impl KeyValueStore) {
+        assert!(store.is_empty());
+
+        // enough keys to extend every table
+        for i in 0..100 {
+            assert!(store.set(&i.to_string(), &format!("value {i}")));
+        }
+
+        assert_eq!(store.len(), 100);
+        assert_eq!(store.get("42"), Some(String::from("value 42")));
+        assert!(store.contains_key("99"));
+        assert!(!store.contains_key("missing"));
+
+        assert!(store.set("42", "updated"));
+        assert_eq!(store.get("42"), Some(String::from("updated")));
+        assert_eq!(store.len(), 100);
+
+        assert_eq!(store.del("42"), Some(String::from("updated")));
+        assert_eq!(store.del("42"), None);
+        assert_eq!(store.get("42"), None);
+        assert_eq!(store.len(), 99);
+    }
+
+    #[test]
+    fn test_hash_table() {
+        exercise(hash::HashTable::new());
+    }
+
+    #[test]
+    fn test_hash_bucket_table() {
+        exercise(hash_bucket::HashTable::new());
+    }
+
+    #[test]
+    fn test_generic_table() {
+        exercise(table::HashTable::<String, String>::new());
+    }
+}
@@ -0,0 +1,95 @@
+use std::sync::RwLock;
+
+use crate::hash::{HashTable, SetError};
+
+/// `hash::HashTable` behind a `RwLock`, so it can be shared across
+/// threads; gets only take the read lock
+#[derive(Default)]
+pub struct SyncHashTable {
+    table: RwLock<HashTable>,
+}
+
+impl SyncHashTable {
+    pub fn new() -> Self {
+        Self {
+            table: RwLock::new(HashTable::new()),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.table.read().expect("Lock poisoned").get(key)
+    }
+
+    pub fn set(&self, key: &str, value: &str) -> Result<Option<String>, SetError> {
+        self.table.write().expect("Lock poisoned").set(key, value)
+    }
+
+    pub fn del(&self, key: &str) -> Option<String> {
+        self.table.write().expect("Lock poisoned").del(key)
+    }
+
+    /// Look up every key under a single read lock, the values come
+    /// back in the same order as the keys
+    pub fn read_many(&self, keys: &[&str]) -> Vec<Option<String>> {
+        let table = self.table.read().expect("Lock poisoned");
+
+        keys.iter().map(|key| table.get(key)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_concurrent_get_and_set() {
+        let hash_table = Arc::new(SyncHashTable::new());
+
+        for i in 0..10 {
+            hash_table.set(&i.to_string(), "initial").unwrap();
+        }
+
+        let writer = {
+            let hash_table = Arc::clone(&hash_table);
+
+            thread::spawn(move || {
+                for i in 10..200 {
+                    hash_table.set(&i.to_string(), "written").unwrap();
+                }
+            })
+        };
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let hash_table = Arc::clone(&hash_table);
+
+                thread::spawn(move || {
+                    for _ in 0..100 {
+                        // keys set before the writer started are always there
+                        assert_eq!(hash_table.get("0"), Some(String::from("initial")));
+
+                        let values = hash_table.read_many(&["1", "9"]);
+                        assert!(values
+                            .iter()
+                            .all(|value| value.as_deref() == Some("initial")));
+                    }
+                })
+            })
+            .collect();
+
+        writer.join().unwrap();
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        assert_eq!(hash_table.get("199"), Some(String::from("written")));
+        assert_eq!(hash_table.del("199"), Some(String::from("written")));
+        assert_eq!(
+            hash_table.read_many(&["0", "199"]),
+            vec![Some(String::from("initial")), None]
+        );
+    }
+}
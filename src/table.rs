@@ -1,5 +1,21 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Index;
+
+use crate::hash_bucket::next_prime;
+
+// no of slots a table starts with, it's never shrunk below this
+const INITIAL_SIZE: usize = 61;
+
 pub trait Hashable {
     fn hash(&self) -> usize;
+
+    /// Hash which is the same on 32 and 64-bit targets, for anything
+    /// written to disk. The default keeps the low 32 bits of `hash`,
+    /// which are portable as long as it only wraps on shifts and adds
+    fn hash_u32(&self) -> u32 {
+        self.hash() as u32
+    }
 }
 
 impl Hashable for String {
@@ -15,32 +31,196 @@ impl Hashable for String {
     }
 }
 
+impl Hashable for &[u8] {
+    // djb2 as well, over the raw bytes
+    fn hash(&self) -> usize {
+        let mut result: usize = 5381;
+
+        for &b in self.iter() {
+            result = ((result << 5).wrapping_add(result)).wrapping_add(b as usize);
+        }
+
+        result
+    }
+
+    // same djb2 wrapping at 32 bits whatever the width of `usize`
+    fn hash_u32(&self) -> u32 {
+        let mut result: u32 = 5381;
+
+        for &b in self.iter() {
+            result = ((result << 5).wrapping_add(result)).wrapping_add(b as u32);
+        }
+
+        result
+    }
+}
+
+impl Hashable for Vec<u8> {
+    fn hash(&self) -> usize {
+        self.as_slice().hash()
+    }
+
+    fn hash_u32(&self) -> u32 {
+        self.as_slice().hash_u32()
+    }
+}
+
 #[derive(Default, Clone, Copy)]
 struct HashItem<Key, Value> {
     key: Key,
     value: Value,
     is_taken: bool,
+    // removed item, probing has to continue past this slot
+    is_deleted: bool,
+}
+
+/// What changed from one table to another, see `HashTable::diff`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableDiff<Key, Value> {
+    /// Keys only in the other table, with their value there
+    pub added: Vec<(Key, Value)>,
+    /// Keys only in this table, with their value here
+    pub removed: Vec<(Key, Value)>,
+    /// Keys in both tables, with the value here and the one there
+    pub changed: Vec<(Key, Value, Value)>,
 }
 
 pub struct HashTable<Key, Value> {
     kvs: Vec<HashItem<Key, Value>>,
     size: usize,
     no_of_taken: usize,
+    // occupancy ratio which triggers an extend on insert
+    max_load_factor: f64,
+    // `Send + Sync` so the table can still be shared across threads
+    hasher: Box<dyn Fn(&Key) -> usize + Send + Sync>,
+    // called with (old_size, new_size) whenever the table is rehashed
+    on_resize: Option<Box<dyn Fn(usize, usize) + Send + Sync>>,
+    // taken slots in insertion order, only kept by `new_ordered` tables
+    order: Option<Vec<usize>>,
+}
+
+impl<Key: Default + Clone + PartialEq + Hashable, Value: Default + Clone> Default
+    for HashTable<Key, Value>
+{
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<Key: Default + Clone + PartialEq + Hashable, Value: Default + Clone> HashTable<Key, Value> {
     pub fn new() -> Self {
-        const INITIAL_SIZE: usize = 61;
+        Self::with_hasher(|key: &Key| key.hash())
+    }
 
+    /// Create a table which extends once `lf` of its slots are taken,
+    /// `new` uses the default of 0.75
+    pub fn with_load_factor(lf: f64) -> Self {
+        assert!(
+            lf > 0.0 && lf < 1.0,
+            "Load factor should be between 0 and 1"
+        );
+
+        Self {
+            max_load_factor: lf,
+            ..Self::new()
+        }
+    }
+
+    /// Create a table which also tracks the insertion order of its
+    /// keys for `iter_ordered`, at the cost of updating it on every
+    /// insert and remove
+    pub fn new_ordered() -> Self {
+        Self {
+            order: Some(Vec::new()),
+            ..Self::new()
+        }
+    }
+}
+
+impl<Key: Default + Clone + PartialEq, Value: Default + Clone> HashTable<Key, Value> {
+    /// Create a table which places keys using `hasher` instead of
+    /// the `Hashable` impl of the key
+    pub fn with_hasher<F: Fn(&Key) -> usize + Send + Sync + 'static>(hasher: F) -> Self {
         Self {
             kvs: vec![HashItem::<_, _>::default(); INITIAL_SIZE],
             size: INITIAL_SIZE,
             no_of_taken: 0,
+            max_load_factor: 0.75,
+            hasher: Box::new(hasher),
+            on_resize: None,
+            order: None,
         }
     }
 
     pub fn insert(&mut self, key: Key, value: Value) {
-        let load_factor = (self.size as f64 * 0.75) as usize;
+        self.insert_index(key, value);
+    }
+
+    /// Insert like `insert`, handing back how many slots were probed
+    /// past the home slot of the key before it was placed
+    pub fn insert_reporting(&mut self, key: Key, value: Value) -> usize {
+        let (_, steps) = self.insert_index(key, value);
+
+        steps
+    }
+
+    /// Mutable reference to the value of the key, `default` is
+    /// inserted first if the key isn't stored yet
+    pub fn entry_or_insert_with<F: FnOnce() -> Value>(
+        &mut self,
+        key: Key,
+        default: F,
+    ) -> &mut Value {
+        // the index is taken after a possible extend
+        let (index, _) = self.entry_index(key, default);
+
+        &mut self.kvs[index].value
+    }
+
+    /// Store `merge(existing, value)` for the key in a single probe,
+    /// `existing` is `None` if the key isn't stored yet
+    pub fn upsert<F: FnOnce(Option<Value>, Value) -> Value>(
+        &mut self,
+        key: Key,
+        value: Value,
+        merge: F,
+    ) {
+        let mut is_new = false;
+
+        let (index, _) = self.entry_index(key, || {
+            is_new = true;
+            Value::default()
+        });
+
+        let slot = &mut self.kvs[index].value;
+        let existing = if is_new {
+            None
+        } else {
+            Some(std::mem::take(slot))
+        };
+
+        *slot = merge(existing, value);
+    }
+
+    // insert the item and hand back the slot it ended up in along
+    // with the probe steps it took
+    fn insert_index(&mut self, key: Key, value: Value) -> (usize, usize) {
+        let mut value = Some(value);
+
+        let (index, steps) = self.entry_index(key, || value.take().unwrap());
+
+        // the key was already stored, the value wasn't used yet
+        if let Some(value) = value {
+            self.kvs[index].value = value;
+        }
+
+        (index, steps)
+    }
+
+    // slot of the key and the probe steps to it in a single probe,
+    // `default` is only called and inserted if the key isn't stored yet
+    fn entry_index<F: FnOnce() -> Value>(&mut self, key: Key, default: F) -> (usize, usize) {
+        let load_factor = (self.size as f64 * self.max_load_factor) as usize;
 
         if self.no_of_taken >= load_factor {
             self.extend();
@@ -48,58 +228,262 @@ impl<Key: Default + Clone + PartialEq + Hashable, Value: Default + Clone> HashTa
 
         let mut index = self.get_hash_index(&key);
 
-        for _ in 0..self.size {
-            if !self.kvs[index].is_taken {
-                self.kvs[index] = HashItem {
-                    key: key.to_owned(),
-                    value: value.to_owned(),
-                    is_taken: true,
-                };
-                self.no_of_taken += 1;
+        // first free slot on the probe chain, a deleted one can be
+        // reused but the key might still be stored further down
+        let mut free_slot: Option<(usize, usize)> = None;
 
-                break;
-            }
+        for steps in 0..self.size {
+            if !self.kvs[index].is_taken {
+                free_slot.get_or_insert((index, steps));
 
-            if self.kvs[index].key == key {
-                self.kvs[index].value = value.to_owned();
+                if !self.kvs[index].is_deleted {
+                    break;
+                }
+            } else if self.kvs[index].key == key {
+                return (index, steps);
             }
 
             index = (index + 1) % self.size;
         }
+
+        // scanned every slot without a free one, grow and retry so
+        // the insert is never dropped
+        let Some((index, steps)) = free_slot else {
+            self.extend();
+            return self.entry_index(key, default);
+        };
+
+        self.kvs[index] = HashItem {
+            key,
+            value: default(),
+            is_taken: true,
+            is_deleted: false,
+        };
+        self.no_of_taken += 1;
+
+        if let Some(order) = &mut self.order {
+            order.push(index);
+        }
+
+        (index, steps)
+    }
+
+    /// Insert every item, the table is grown once up front for the
+    /// incoming items instead of extending while they go in
+    pub fn insert_many(&mut self, items: impl IntoIterator<Item = (Key, Value)>) {
+        let items = items.into_iter();
+
+        // the lower bound is exact for an `ExactSizeIterator` and
+        // never over-allocates for filtered ones
+        let (lower, _) = items.size_hint();
+
+        self.reserve(lower);
+
+        for (key, value) in items {
+            self.insert(key, value);
+        }
+    }
+
+    /// Grow the table once so `additional` more keys can be inserted
+    /// without an extend (and its rehash) in between
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self.no_of_taken + additional;
+        let mut new_size = self.size;
+
+        // same steps as `extend`, and `insert` extends once the
+        // taken slots reach the load factor
+        while needed >= (new_size as f64 * self.max_load_factor) as usize {
+            new_size = (new_size * 2) + 1;
+        }
+
+        if new_size != self.size {
+            self.resize(new_size);
+        }
     }
 
     pub fn get(&self, key: &Key) -> Option<&Value> {
-        if let Some(index) = self.get_index(&key) {
+        if let Some(index) = self.get_index(key) {
             Some(&self.kvs[index].value)
         } else {
             None
         }
     }
 
+    /// Stored key along with its value. The key is the one from the
+    /// first insert, which may differ from `key` for keys whose
+    /// `PartialEq` treats different values as the same key
+    pub fn get_key_value(&self, key: &Key) -> Option<(&Key, &Value)> {
+        let index = self.get_index(key)?;
+        let item = &self.kvs[index];
+
+        Some((&item.key, &item.value))
+    }
+
     pub fn get_mut(&mut self, key: &Key) -> Option<&mut Value> {
-        if let Some(index) = self.get_index(&key) {
+        if let Some(index) = self.get_index(key) {
             Some(&mut self.kvs[index].value)
         } else {
             None
         }
     }
 
+    /// Remove the key and hand back its value, the slot is marked as
+    /// deleted so keys further down its probe chain stay reachable
+    pub fn remove(&mut self, key: &Key) -> Option<Value> {
+        let index = self.get_index(key)?;
+
+        let item = std::mem::take(&mut self.kvs[index]);
+
+        self.kvs[index].is_deleted = true;
+        self.no_of_taken -= 1;
+
+        if let Some(order) = &mut self.order {
+            order.retain(|&i| i != index);
+        }
+
+        Some(item.value)
+    }
+
+    /// Keep only the items `f` returns true for, the others are
+    /// marked deleted like in `remove` so no probe chain is broken
+    pub fn retain<F: FnMut(&Key, &Value) -> bool>(&mut self, mut f: F) {
+        for item in self.kvs.iter_mut() {
+            if item.is_taken && !f(&item.key, &item.value) {
+                *item = HashItem::default();
+
+                item.is_deleted = true;
+                self.no_of_taken -= 1;
+            }
+        }
+
+        if let Some(order) = &mut self.order {
+            let kvs = &self.kvs;
+            order.retain(|&i| kvs[i].is_taken);
+        }
+    }
+
+    /// Number of stored keys
+    pub fn len(&self) -> usize {
+        self.no_of_taken
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.no_of_taken == 0
+    }
+
+    /// Number of slots, taken or not. Unlike `len` it only changes
+    /// when the table is resized
+    pub fn capacity(&self) -> usize {
+        self.size
+    }
+
+    /// Ratio of taken slots to the size of the table
+    pub fn load_factor(&self) -> f64 {
+        self.no_of_taken as f64 / self.size as f64
+    }
+
+    /// Bytes allocated for the slots plus the table itself. Empty
+    /// slots count too while heap data owned by the keys and values
+    /// doesn't, it's not the size of the stored data
+    pub fn memory_footprint(&self) -> usize {
+        self.kvs.capacity() * std::mem::size_of::<HashItem<Key, Value>>()
+            + std::mem::size_of::<Self>()
+    }
+
+    /// Remove every key, the table keeps its current size
+    pub fn clear(&mut self) {
+        self.kvs.fill_with(HashItem::default);
+        self.no_of_taken = 0;
+
+        if let Some(order) = &mut self.order {
+            order.clear();
+        }
+    }
+
+    /// Stored (key, value) pairs in insertion order for a table made
+    /// with `new_ordered`, in slot order for any other table
+    pub fn iter_ordered(&self) -> impl Iterator<Item = (&Key, &Value)> + '_ {
+        let ordered = self.order.iter().flatten().copied();
+        let unordered = (0..self.size).filter(|&i| self.order.is_none() && self.kvs[i].is_taken);
+
+        ordered
+            .chain(unordered)
+            .map(|i| (&self.kvs[i].key, &self.kvs[i].value))
+    }
+
+    /// Check if the key is stored without handing out its value
+    pub fn contains_key(&self, key: &Key) -> bool {
+        self.get_index(key).is_some()
+    }
+
+    /// Insert every item of `other`, keys which are already
+    /// stored get the value from `other`
+    pub fn extend_from(&mut self, other: &HashTable<Key, Value>) {
+        for (key, value) in other.items() {
+            self.insert(key.clone(), value.clone());
+        }
+    }
+
+    /// Rehash into the smallest prime size which holds the stored
+    /// keys under the load factor, but never below the initial 61
+    pub fn shrink_to_fit(&mut self) {
+        let mut new_size = INITIAL_SIZE;
+
+        while self.no_of_taken >= (new_size as f64 * self.max_load_factor) as usize {
+            new_size = next_prime(new_size + 1);
+        }
+
+        if new_size < self.size {
+            self.resize(new_size);
+        }
+    }
+
     pub fn extend(&mut self) {
-        let new_size = (self.size * 2) + 1;
+        self.resize((self.size * 2) + 1);
+    }
 
-        let mut new_self = Self {
-            kvs: vec![HashItem::<_, _>::default(); new_size],
-            size: new_size,
-            no_of_taken: self.no_of_taken,
-        };
+    /// Register `cb` to be called with the old and new size every time
+    /// the table is rehashed into a new size, replacing any earlier one
+    pub fn on_resize(&mut self, cb: Box<dyn Fn(usize, usize) + Send + Sync>) {
+        self.on_resize = Some(cb);
+    }
+
+    fn resize(&mut self, new_size: usize) {
+        if let Some(cb) = &self.on_resize {
+            cb(self.size, new_size);
+        }
+
+        // the hasher can't be cloned into a new table, so
+        // swap in the bigger buffer and reinsert in place
+        let mut old_kvs =
+            std::mem::replace(&mut self.kvs, vec![HashItem::<_, _>::default(); new_size]);
+
+        self.size = new_size;
+        self.no_of_taken = 0;
+
+        // reinserted in insertion order, so the new order matches
+        if let Some(order) = self.order.as_mut().map(std::mem::take) {
+            for index in order {
+                let item = std::mem::take(&mut old_kvs[index]);
+                self.insert(item.key, item.value);
+            }
+
+            return;
+        }
 
-        for item in self.kvs.iter() {
+        for item in old_kvs {
             if item.is_taken {
-                new_self.insert(item.key.to_owned(), item.value.to_owned());
+                self.insert(item.key, item.value);
             }
         }
+    }
 
-        *self = new_self;
+    // key and value of every taken slot
+    fn items(&self) -> impl Iterator<Item = (&Key, &Value)> + '_ {
+        self.kvs
+            .iter()
+            .filter(|item| item.is_taken)
+            .map(|item| (&item.key, &item.value))
     }
 
     fn get_index(&self, key: &Key) -> Option<usize> {
@@ -107,27 +491,209 @@ impl<Key: Default + Clone + PartialEq + Hashable, Value: Default + Clone> HashTa
 
         for _ in 0..self.size {
             // if no item found
-            if !self.kvs[index].is_taken {
+            if !self.kvs[index].is_taken && !self.kvs[index].is_deleted {
                 break;
             }
 
             // if item found
-            if self.kvs[index].key == *key {
-                break;
+            if self.kvs[index].is_taken && self.kvs[index].key == *key {
+                return Some(index);
             }
 
             index = (index + 1) % self.size;
         }
 
-        if self.kvs[index].is_taken && self.kvs[index].key == *key {
-            Some(index)
-        } else {
-            None
-        }
+        None
     }
 
     fn get_hash_index(&self, key: &Key) -> usize {
-        key.hash() % self.size
+        (self.hasher)(key) % self.size
+    }
+}
+
+impl<Key: Default + Clone + PartialEq + Hashable, Value: Default + Clone> FromIterator<(Key, Value)>
+    for HashTable<Key, Value>
+{
+    fn from_iter<I: IntoIterator<Item = (Key, Value)>>(iter: I) -> Self {
+        let mut hash_table = Self::new();
+
+        // sized from the size hint once, then filled
+        hash_table.insert_many(iter);
+
+        hash_table
+    }
+}
+
+/// Owning iterator over the stored (key, value) pairs of a table
+pub struct IntoIter<Key, Value> {
+    kvs: std::vec::IntoIter<HashItem<Key, Value>>,
+}
+
+impl<Key, Value> Iterator for IntoIter<Key, Value> {
+    type Item = (Key, Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.kvs
+            .find(|item| item.is_taken)
+            .map(|item| (item.key, item.value))
+    }
+}
+
+// the slots are moved out in slot order, nothing is cloned
+impl<Key, Value> IntoIterator for HashTable<Key, Value> {
+    type Item = (Key, Value);
+    type IntoIter = IntoIter<Key, Value>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            kvs: self.kvs.into_iter(),
+        }
+    }
+}
+
+impl<Key: Default + Clone + PartialEq, Value: Default + Clone> Index<&Key>
+    for HashTable<Key, Value>
+{
+    type Output = Value;
+
+    /// Same as `get`, but panics if the key isn't stored like the std map
+    fn index(&self, key: &Key) -> &Value {
+        self.get(key).expect("Key not found in the table")
+    }
+}
+
+impl<Key: fmt::Debug, Value: fmt::Debug> fmt::Debug for HashTable<Key, Value> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Size: {}", self.size)?;
+        writeln!(f, "Taken: {}", self.no_of_taken)?;
+        writeln!(f, "----------------")?;
+
+        for (index, item) in self.kvs.iter().enumerate() {
+            if item.is_taken {
+                writeln!(f, "[{index}] {:?}: {:?}", item.key, item.value)?;
+            }
+        }
+
+        write!(f, "----------------")
+    }
+}
+
+impl<Key: fmt::Debug, Value: fmt::Debug> HashTable<Key, Value> {
+    /// Same as the `Debug` output, for logging
+    pub fn dump(&self) -> String {
+        format!("{self:?}")
+    }
+}
+
+impl<Key: Default + Clone + PartialEq> HashTable<Key, u64> {
+    /// Add one to the count of the key, a missing key starts at 0
+    pub fn increment(&mut self, key: Key) {
+        *self.entry_or_insert_with(key, || 0) += 1;
+    }
+
+    /// Count of the key, 0 if it was never incremented
+    pub fn get_count(&self, key: &Key) -> u64 {
+        self.get(key).copied().unwrap_or(0)
+    }
+}
+
+impl<Key: Default + Clone + PartialEq, Value: Default + Clone + PartialEq> HashTable<Key, Value> {
+    /// Changes which turn this table into `other`, each list is in
+    /// the slot order of the table the keys were found in
+    pub fn diff(&self, other: &HashTable<Key, Value>) -> TableDiff<Key, Value> {
+        let mut diff = TableDiff {
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        };
+
+        for (key, value) in self.items() {
+            match other.get(key) {
+                None => diff.removed.push((key.clone(), value.clone())),
+                Some(other_value) if other_value != value => {
+                    diff.changed
+                        .push((key.clone(), value.clone(), other_value.clone()));
+                }
+                Some(_) => {}
+            }
+        }
+
+        for (key, value) in other.items() {
+            if !self.contains_key(key) {
+                diff.added.push((key.clone(), value.clone()));
+            }
+        }
+
+        diff
+    }
+}
+
+impl<Key: Default + Clone + Eq + std::hash::Hash, Value: Default + Clone> HashTable<Key, Value> {
+    /// Copy the stored pairs into a std `HashMap`
+    pub fn to_hashmap(&self) -> HashMap<Key, Value> {
+        self.items()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect()
+    }
+}
+
+// only taken slots are written, as a map
+#[cfg(feature = "serde")]
+impl<Key: serde::Serialize, Value: serde::Serialize> serde::Serialize for HashTable<Key, Value> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(Some(self.no_of_taken))?;
+
+        for item in self.kvs.iter().filter(|item| item.is_taken) {
+            map.serialize_entry(&item.key, &item.value)?;
+        }
+
+        map.end()
+    }
+}
+
+// rebuilt through `insert`, so the new table decides its own
+// size and slots
+#[cfg(feature = "serde")]
+impl<'de, Key, Value> serde::Deserialize<'de> for HashTable<Key, Value>
+where
+    Key: serde::Deserialize<'de> + Default + Clone + PartialEq + Hashable,
+    Value: serde::Deserialize<'de> + Default + Clone,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TableVisitor<Key, Value>(std::marker::PhantomData<(Key, Value)>);
+
+        impl<'de, Key, Value> serde::de::Visitor<'de> for TableVisitor<Key, Value>
+        where
+            Key: serde::Deserialize<'de> + Default + Clone + PartialEq + Hashable,
+            Value: serde::Deserialize<'de> + Default + Clone,
+        {
+            type Value = HashTable<Key, Value>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a map of keys to values")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut access: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut hash_table = HashTable::new();
+
+                // the hint comes from the input, so it's capped the same
+                // way serde caps it, `insert` grows past that
+                hash_table.reserve(access.size_hint().unwrap_or(0).min(4096));
+
+                while let Some((key, value)) = access.next_entry()? {
+                    hash_table.insert(key, value);
+                }
+
+                Ok(hash_table)
+            }
+        }
+
+        deserializer.deserialize_map(TableVisitor(std::marker::PhantomData))
     }
 }
 
@@ -157,46 +723,778 @@ mod tests {
     }
 
     #[test]
-    fn test_get_mut() {
+    fn test_overwrite_keeps_single_slot() {
         let mut hash_table = HashTable::<String, usize>::new();
-        hash_table.insert("mutable".to_string(), 5);
+        hash_table.insert("key".to_string(), 10);
+        hash_table.insert("other".to_string(), 1);
 
-        // Modify the value through a mutable reference
-        if let Some(value) = hash_table.get_mut(&"mutable".to_string()) {
-            *value = 15;
-        }
+        assert_eq!(hash_table.no_of_taken, 2);
 
-        assert_eq!(hash_table.get(&"mutable".to_string()), Some(&15)); // Check updated value
+        hash_table.insert("key".to_string(), 20);
+        hash_table.insert("key".to_string(), 30);
+
+        // no second slot was allocated for the overwritten key
+        assert_eq!(hash_table.no_of_taken, 2);
+        assert_eq!(
+            hash_table
+                .kvs
+                .iter()
+                .filter(|item| item.is_taken && item.key == "key")
+                .count(),
+            1
+        );
+        assert_eq!(hash_table.get(&"key".to_string()), Some(&30));
     }
 
     #[test]
-    fn test_extend() {
+    fn test_remove_in_collision_chain() {
+        // every key lands on the same slot, so they form one chain
+        let mut hash_table = HashTable::<String, usize>::with_hasher(|_: &String| 3);
+
+        hash_table.insert("a".to_string(), 1);
+        hash_table.insert("b".to_string(), 2);
+        hash_table.insert("c".to_string(), 3);
+
+        assert_eq!(hash_table.remove(&"b".to_string()), Some(2));
+        assert_eq!(hash_table.remove(&"b".to_string()), None);
+        assert_eq!(hash_table.no_of_taken, 2);
+
+        assert_eq!(hash_table.get(&"a".to_string()), Some(&1));
+        assert_eq!(hash_table.get(&"b".to_string()), None);
+        assert_eq!(hash_table.get(&"c".to_string()), Some(&3));
+
+        // updating a key past the deleted slot doesn't duplicate it
+        hash_table.insert("c".to_string(), 30);
+        assert_eq!(hash_table.no_of_taken, 2);
+        assert_eq!(hash_table.get(&"c".to_string()), Some(&30));
+
+        // the deleted slot is reused
+        hash_table.insert("d".to_string(), 4);
+        assert_eq!(hash_table.no_of_taken, 3);
+        assert_eq!(hash_table.get(&"d".to_string()), Some(&4));
+    }
+
+    #[test]
+    fn test_len() {
         let mut hash_table = HashTable::<String, usize>::new();
+        assert!(hash_table.is_empty());
 
-        // Insert enough items to trigger table extension
+        // enough to extend the table
         for i in 0..100 {
-            let key = format!("key_{}", i);
-            hash_table.insert(key.clone(), i);
+            hash_table.insert(format!("key_{}", i), i);
         }
 
-        // Check a few keys to ensure they're still accessible after resizing
-        assert_eq!(hash_table.get(&"key_0".to_string()), Some(&0));
-        assert_eq!(hash_table.get(&"key_50".to_string()), Some(&50));
-        assert_eq!(hash_table.get(&"key_99".to_string()), Some(&99));
+        assert_eq!(hash_table.len(), 100);
+
+        hash_table.remove(&"key_0".to_string());
+        assert_eq!(hash_table.len(), 99);
+        assert!(!hash_table.is_empty());
     }
 
     #[test]
-    fn test_extend_and_overwrite() {
+    fn test_clear() {
         let mut hash_table = HashTable::<String, usize>::new();
 
-        // Insert a large number of items to trigger table resizing
         for i in 0..100 {
-            let key = format!("key_{}", i);
-            hash_table.insert(key.clone(), i);
+            hash_table.insert(format!("key_{}", i), i);
         }
 
-        // Overwrite an existing key
-        hash_table.insert("key_50".to_string(), 500);
-        assert_eq!(hash_table.get(&"key_50".to_string()), Some(&500));
+        let size = hash_table.size;
+
+        hash_table.clear();
+
+        assert_eq!(hash_table.len(), 0);
+        assert_eq!(hash_table.size, size);
+        assert_eq!(hash_table.get(&"key_0".to_string()), None);
+        assert!(hash_table.kvs.iter().all(|item| !item.is_taken));
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut hash_table = HashTable::<String, usize>::new();
+        hash_table.insert("mutable".to_string(), 5);
+
+        // Modify the value through a mutable reference
+        if let Some(value) = hash_table.get_mut(&"mutable".to_string()) {
+            *value = 15;
+        }
+
+        assert_eq!(hash_table.get(&"mutable".to_string()), Some(&15)); // Check updated value
+    }
+
+    #[test]
+    fn test_contains_key() {
+        let mut hash_table = HashTable::<String, usize>::new();
+        hash_table.insert("present".to_string(), 1);
+
+        assert!(hash_table.contains_key(&"present".to_string()));
+        assert!(!hash_table.contains_key(&"missing".to_string()));
+    }
+
+    #[test]
+    fn test_with_hasher() {
+        // FNV-1a
+        let mut hash_table = HashTable::<String, usize>::with_hasher(|key: &String| {
+            let mut result: u64 = 0xcbf29ce484222325;
+
+            for b in key.bytes() {
+                result = (result ^ b as u64).wrapping_mul(0x100000001b3);
+            }
+
+            result as usize
+        });
+
+        for i in 0..100 {
+            hash_table.insert(format!("key_{}", i), i);
+        }
+
+        assert_eq!(hash_table.get(&"key_0".to_string()), Some(&0));
+        assert_eq!(hash_table.get(&"key_99".to_string()), Some(&99));
+        assert_eq!(hash_table.get(&"missing".to_string()), None);
+    }
+
+    #[test]
+    fn test_with_constant_hasher() {
+        // every key collides, lookups still work through probing
+        let mut hash_table = HashTable::<String, usize>::with_hasher(|_: &String| 7);
+
+        hash_table.insert("a".to_string(), 1);
+        hash_table.insert("b".to_string(), 2);
+
+        assert_eq!(hash_table.get(&"a".to_string()), Some(&1));
+        assert_eq!(hash_table.get(&"b".to_string()), Some(&2));
+    }
+
+    #[test]
+    fn test_insert_all_colliding_keys() {
+        let mut hash_table = HashTable::<String, usize>::with_hasher(|_: &String| 7);
+        let size = hash_table.size;
+
+        // as many keys as slots, all on the same probe chain
+        for i in 0..size {
+            hash_table.insert(format!("key_{}", i), i);
+        }
+
+        assert_eq!(hash_table.len(), size);
+
+        for i in 0..size {
+            assert_eq!(hash_table.get(&format!("key_{}", i)), Some(&i));
+        }
+    }
+
+    #[test]
+    fn test_insert_into_taken_slots() {
+        let mut hash_table = HashTable::<String, usize>::new();
+        let size = hash_table.size;
+
+        // take every slot behind the counter's back, so the load
+        // check doesn't extend the table before the scan
+        for (i, item) in hash_table.kvs.iter_mut().enumerate() {
+            *item = HashItem {
+                key: format!("taken_{i}"),
+                value: i,
+                is_taken: true,
+                is_deleted: false,
+            };
+        }
+
+        // no free slot on the chain, the table grows and retries
+        hash_table.insert("new".to_string(), 1);
+
+        assert!(hash_table.size > size);
+        assert_eq!(hash_table.get(&"new".to_string()), Some(&1));
+        assert_eq!(hash_table.get(&"taken_0".to_string()), Some(&0));
+        assert_eq!(hash_table.len(), size + 1);
+    }
+
+    #[test]
+    fn test_insert_reporting() {
+        let mut hash_table = HashTable::<String, usize>::with_hasher(|_: &String| 7);
+
+        assert_eq!(hash_table.insert_reporting("a".to_string(), 1), 0);
+        assert_eq!(hash_table.insert_reporting("b".to_string(), 2), 1);
+        assert_eq!(hash_table.insert_reporting("c".to_string(), 3), 2);
+        assert_eq!(hash_table.insert_reporting("d".to_string(), 4), 3);
+
+        // overwriting walks the same chain
+        assert_eq!(hash_table.insert_reporting("b".to_string(), 20), 1);
+        assert_eq!(hash_table.get(&"b".to_string()), Some(&20));
+    }
+
+    #[test]
+    fn test_hash_u32() {
+        // djb2 of "hello" wrapped at 32 bits
+        assert_eq!(b"hello".as_slice().hash_u32(), 261238937);
+        assert_eq!(b"hello".to_vec().hash_u32(), 261238937);
+        assert_eq!("hello".to_string().hash_u32(), 261238937);
+
+        // the low bits of the native hash agree
+        assert_eq!("hello".to_string().hash() as u32, 261238937);
+    }
+
+    #[test]
+    fn test_extend() {
+        let mut hash_table = HashTable::<String, usize>::new();
+
+        // Insert enough items to trigger table extension
+        for i in 0..100 {
+            let key = format!("key_{}", i);
+            hash_table.insert(key.clone(), i);
+        }
+
+        // Check a few keys to ensure they're still accessible after resizing
+        assert_eq!(hash_table.get(&"key_0".to_string()), Some(&0));
+        assert_eq!(hash_table.get(&"key_50".to_string()), Some(&50));
+        assert_eq!(hash_table.get(&"key_99".to_string()), Some(&99));
+    }
+
+    #[test]
+    fn test_extend_and_overwrite() {
+        let mut hash_table = HashTable::<String, usize>::new();
+
+        // Insert a large number of items to trigger table resizing
+        for i in 0..100 {
+            let key = format!("key_{}", i);
+            hash_table.insert(key.clone(), i);
+        }
+
+        // Overwrite an existing key
+        hash_table.insert("key_50".to_string(), 500);
+        assert_eq!(hash_table.get(&"key_50".to_string()), Some(&500));
+    }
+
+    #[test]
+    fn test_with_load_factor() {
+        let mut hash_table = HashTable::<String, usize>::with_load_factor(0.25);
+        assert_eq!(hash_table.load_factor(), 0.0);
+
+        for i in 0..100 {
+            hash_table.insert(format!("key_{}", i), i);
+
+            assert!(hash_table.load_factor() <= 0.25 + 1.0 / hash_table.size as f64);
+        }
+
+        assert_eq!(hash_table.len(), 100);
+        assert_eq!(hash_table.get(&"key_99".to_string()), Some(&99));
+    }
+
+    #[test]
+    #[should_panic(expected = "Load factor should be between 0 and 1")]
+    fn test_with_load_factor_out_of_range() {
+        HashTable::<String, usize>::with_load_factor(1.0);
+    }
+
+    #[test]
+    fn test_insert_many() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        // every (re)insert hashes the key once, so extending while
+        // inserting shows up as extra calls
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+
+        let mut hash_table = HashTable::<String, usize>::with_hasher(move |key: &String| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            key.hash()
+        });
+
+        let items: Vec<_> = (0..10_000).map(|i| (format!("key_{}", i), i)).collect();
+
+        hash_table.insert_many(items);
+
+        assert_eq!(calls.load(Ordering::Relaxed), 10_000);
+        assert_eq!(hash_table.len(), 10_000);
+        assert_eq!(hash_table.get(&"key_0".to_string()), Some(&0));
+        assert_eq!(hash_table.get(&"key_9999".to_string()), Some(&9999));
+    }
+
+    #[test]
+    fn test_entry_or_insert_with() {
+        let mut hash_table = HashTable::<String, usize>::new();
+
+        for word in "a b a c a b".split(' ') {
+            *hash_table.entry_or_insert_with(word.to_string(), || 0) += 1;
+        }
+
+        assert_eq!(hash_table.get(&"a".to_string()), Some(&3));
+        assert_eq!(hash_table.get(&"b".to_string()), Some(&2));
+        assert_eq!(hash_table.get(&"c".to_string()), Some(&1));
+        assert_eq!(hash_table.len(), 3);
+
+        // the key which triggers an extend still gets a valid reference
+        for i in 0..100 {
+            *hash_table.entry_or_insert_with(format!("key_{}", i), || i) += 1;
+        }
+
+        assert_eq!(hash_table.get(&"key_0".to_string()), Some(&1));
+        assert_eq!(hash_table.get(&"key_99".to_string()), Some(&100));
+    }
+
+    #[test]
+    fn test_dump() {
+        let mut hash_table = HashTable::<String, usize>::with_hasher(|_: &String| 3);
+
+        hash_table.insert("a".to_string(), 1);
+        hash_table.insert("b".to_string(), 2);
+        hash_table.remove(&"a".to_string());
+
+        let dump = hash_table.dump();
+
+        assert!(dump.contains("Size: 61"));
+        assert!(dump.contains("Taken: 1"));
+        assert!(dump.contains("[4] \"b\": 2"));
+        // removed and empty slots are skipped
+        assert!(!dump.contains("\"a\""));
+        assert_eq!(dump.lines().count(), 5);
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let pairs: Vec<(String, usize)> = (0..100).map(|i| (format!("key_{}", i), i)).collect();
+
+        let hash_table: HashTable<String, usize> = pairs.into_iter().collect();
+
+        assert_eq!(hash_table.len(), 100);
+        assert_eq!(hash_table.get(&"key_0".to_string()), Some(&0));
+        assert_eq!(hash_table.get(&"key_99".to_string()), Some(&99));
+        assert_eq!(hash_table.get(&"missing".to_string()), None);
+    }
+
+    #[test]
+    fn test_reserve() {
+        let mut hash_table = HashTable::<String, usize>::new();
+
+        hash_table.reserve(10_000);
+
+        let size = hash_table.size;
+
+        for i in 0..10_000 {
+            hash_table.insert(format!("key_{}", i), i);
+
+            assert_eq!(hash_table.size, size);
+        }
+
+        assert_eq!(hash_table.get(&"key_9999".to_string()), Some(&9999));
+
+        // nothing to do when there's already room
+        hash_table.reserve(0);
+        assert_eq!(hash_table.size, size);
+    }
+
+    #[test]
+    fn test_extend_from() {
+        let mut hash_table = HashTable::<String, usize>::new();
+        hash_table.insert("a".to_string(), 1);
+        hash_table.insert("shared".to_string(), 2);
+
+        let mut other = HashTable::<String, usize>::new();
+        other.insert("shared".to_string(), 20);
+        other.insert("b".to_string(), 3);
+
+        hash_table.extend_from(&other);
+
+        assert_eq!(hash_table.len(), 3);
+        assert_eq!(hash_table.get(&"a".to_string()), Some(&1));
+        assert_eq!(hash_table.get(&"shared".to_string()), Some(&20));
+        assert_eq!(hash_table.get(&"b".to_string()), Some(&3));
+
+        // other is left as it was
+        assert_eq!(other.len(), 2);
+    }
+
+    #[test]
+    fn test_shrink_to_fit() {
+        let mut hash_table = HashTable::<String, usize>::new();
+
+        for i in 0..1000 {
+            hash_table.insert(format!("key_{}", i), i);
+        }
+
+        for i in 10..1000 {
+            hash_table.remove(&format!("key_{}", i));
+        }
+
+        let size = hash_table.size;
+
+        hash_table.shrink_to_fit();
+
+        assert_eq!(hash_table.size, 61);
+        assert!(hash_table.size < size / 10);
+        assert_eq!(hash_table.len(), 10);
+
+        for i in 0..10 {
+            assert_eq!(hash_table.get(&format!("key_{}", i)), Some(&i));
+        }
+
+        // a fuller table only shrinks as far as the load factor allows
+        for i in 0..100 {
+            hash_table.insert(format!("key_{}", i), i);
+        }
+
+        hash_table.reserve(1000);
+        hash_table.shrink_to_fit();
+
+        assert_eq!(hash_table.size, 137);
+        assert_eq!(hash_table.get(&"key_99".to_string()), Some(&99));
+    }
+
+    #[test]
+    fn test_index() {
+        let mut hash_table = HashTable::<String, usize>::new();
+        hash_table.insert("key".to_string(), 42);
+
+        assert_eq!(hash_table[&"key".to_string()], 42);
+    }
+
+    #[test]
+    #[should_panic(expected = "Key not found in the table")]
+    fn test_index_missing_key() {
+        let hash_table = HashTable::<String, usize>::new();
+
+        let _ = hash_table[&"missing".to_string()];
+    }
+
+    #[test]
+    fn test_retain() {
+        let mut hash_table = HashTable::<String, usize>::new();
+
+        for i in 0..100 {
+            hash_table.insert(format!("key_{}", i), i);
+        }
+
+        hash_table.retain(|_, value| value % 2 == 0);
+
+        assert_eq!(hash_table.len(), 50);
+
+        for i in 0..100 {
+            let value = hash_table.get(&format!("key_{}", i));
+
+            if i % 2 == 0 {
+                assert_eq!(value, Some(&i));
+            } else {
+                assert_eq!(value, None);
+            }
+        }
+
+        // keys past a removed slot in a collision chain stay reachable
+        let mut hash_table = HashTable::<String, usize>::with_hasher(|_: &String| 3);
+
+        for (i, key) in ["a", "b", "c"].into_iter().enumerate() {
+            hash_table.insert(key.to_string(), i);
+        }
+
+        hash_table.retain(|key, _| key != "a");
+
+        assert_eq!(hash_table.get(&"a".to_string()), None);
+        assert_eq!(hash_table.get(&"c".to_string()), Some(&2));
+    }
+
+    #[test]
+    fn test_byte_keys() {
+        let mut hash_table = HashTable::<Vec<u8>, usize>::new();
+
+        hash_table.insert(vec![0, 159, 146, 150], 1);
+        hash_table.insert(b"plain".to_vec(), 2);
+
+        assert_eq!(hash_table.get(&vec![0, 159, 146, 150]), Some(&1));
+        assert_eq!(hash_table.get(&b"plain".to_vec()), Some(&2));
+        assert_eq!(hash_table.get(&vec![0, 159, 146]), None);
+
+        // same mixing for both, and for ascii it matches the string keys
+        assert_eq!(b"plain".to_vec().hash(), (&b"plain"[..]).hash());
+        assert_eq!((&b"plain"[..]).hash(), "plain".to_string().hash());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut hash_table = HashTable::<String, usize>::new();
+
+        for i in 0..100 {
+            hash_table.insert(format!("key_{}", i), i);
+        }
+
+        hash_table.remove(&"key_0".to_string());
+
+        let json = serde_json::to_string(&hash_table).unwrap();
+        assert!(json.contains("\"key_99\":99"));
+
+        let restored: HashTable<String, usize> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(restored.len(), 99);
+        assert_eq!(restored.get(&"key_0".to_string()), None);
+
+        for i in 1..100 {
+            assert_eq!(restored.get(&format!("key_{}", i)), Some(&i));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_huge_size_hint() {
+        use serde::Deserialize;
+
+        // claims far more entries than it yields
+        struct Lying(std::vec::IntoIter<(String, usize)>);
+
+        impl Iterator for Lying {
+            type Item = (String, usize);
+
+            fn next(&mut self) -> Option<Self::Item> {
+                self.0.next()
+            }
+
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (usize::MAX, Some(usize::MAX))
+            }
+        }
+
+        let entries = Lying(vec![("a".to_string(), 1), ("b".to_string(), 2)].into_iter());
+        let deserializer =
+            serde::de::value::MapDeserializer::<_, serde::de::value::Error>::new(entries);
+
+        let restored = HashTable::<String, usize>::deserialize(deserializer).unwrap();
+
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored.get(&"b".to_string()), Some(&2));
+        assert!(restored.capacity() < 8192);
+    }
+
+    #[test]
+    fn test_increment() {
+        let mut hash_table = HashTable::<String, u64>::new();
+
+        for _ in 0..1000 {
+            hash_table.increment("hits".to_string());
+        }
+
+        hash_table.increment("other".to_string());
+
+        assert_eq!(hash_table.get_count(&"hits".to_string()), 1000);
+        assert_eq!(hash_table.get_count(&"other".to_string()), 1);
+        assert_eq!(hash_table.get_count(&"missing".to_string()), 0);
+        assert_eq!(hash_table.len(), 2);
+    }
+
+    #[test]
+    fn test_on_resize() {
+        use std::sync::{Arc, Mutex};
+
+        let resizes = Arc::new(Mutex::new(Vec::new()));
+        let log = Arc::clone(&resizes);
+
+        let mut hash_table = HashTable::<String, usize>::new();
+
+        hash_table.on_resize(Box::new(move |old_size, new_size| {
+            log.lock().unwrap().push((old_size, new_size));
+        }));
+
+        // extends at 46 and 93 taken slots
+        for i in 0..100 {
+            hash_table.insert(format!("key_{}", i), i);
+        }
+
+        assert_eq!(*resizes.lock().unwrap(), vec![(61, 123), (123, 247)]);
+    }
+
+    #[test]
+    fn test_memory_footprint() {
+        let mut hash_table = HashTable::<String, usize>::new();
+        hash_table.insert("key".to_string(), 1);
+
+        let footprint = hash_table.memory_footprint();
+        assert!(footprint >= INITIAL_SIZE * std::mem::size_of::<HashItem<String, usize>>());
+
+        hash_table.extend();
+
+        assert!(hash_table.memory_footprint() > footprint);
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut before = HashTable::<String, usize>::new();
+        before.insert("same".to_string(), 1);
+        before.insert("removed".to_string(), 2);
+        before.insert("changed".to_string(), 3);
+
+        let mut after = HashTable::<String, usize>::new();
+        after.insert("same".to_string(), 1);
+        after.insert("changed".to_string(), 30);
+        after.insert("added".to_string(), 4);
+
+        let diff = before.diff(&after);
+
+        assert_eq!(diff.added, vec![("added".to_string(), 4)]);
+        assert_eq!(diff.removed, vec![("removed".to_string(), 2)]);
+        assert_eq!(diff.changed, vec![("changed".to_string(), 3, 30)]);
+
+        // nothing changed against itself
+        let diff = after.diff(&after);
+
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert!(diff.changed.is_empty());
+    }
+
+    #[test]
+    fn test_upsert() {
+        let mut hash_table = HashTable::<String, usize>::new();
+        let sum = |existing: Option<usize>, value| existing.unwrap_or(0) + value;
+
+        hash_table.upsert("total".to_string(), 5, sum);
+        hash_table.upsert("total".to_string(), 10, sum);
+        hash_table.upsert("total".to_string(), 1, sum);
+
+        assert_eq!(hash_table.get(&"total".to_string()), Some(&16));
+        assert_eq!(hash_table.len(), 1);
+
+        // the first merge for a key gets no existing value
+        hash_table.upsert("new".to_string(), 3, |existing, value| {
+            assert_eq!(existing, None);
+            value
+        });
+
+        assert_eq!(hash_table.get(&"new".to_string()), Some(&3));
+    }
+
+    #[test]
+    fn test_into_iter() {
+        let mut hash_table = HashTable::<String, usize>::new();
+
+        for i in 0..100 {
+            hash_table.insert(format!("key_{}", i), i);
+        }
+
+        hash_table.remove(&"key_0".to_string());
+
+        let mut items: Vec<(String, usize)> = hash_table.into_iter().collect();
+        items.sort_by_key(|(_, value)| *value);
+
+        assert_eq!(items.len(), 99);
+
+        for (i, (key, value)) in items.into_iter().enumerate() {
+            assert_eq!(key, format!("key_{}", i + 1));
+            assert_eq!(value, i + 1);
+        }
+    }
+
+    #[test]
+    fn test_new_ordered() {
+        let mut hash_table = HashTable::<String, usize>::new_ordered();
+
+        hash_table.insert("c".to_string(), 1);
+        hash_table.insert("a".to_string(), 2);
+        hash_table.insert("b".to_string(), 3);
+
+        // overwriting keeps the first position
+        hash_table.insert("c".to_string(), 10);
+
+        let keys: Vec<&String> = hash_table.iter_ordered().map(|(key, _)| key).collect();
+        assert_eq!(keys, vec!["c", "a", "b"]);
+
+        hash_table.remove(&"a".to_string());
+
+        // the order survives the table being extended
+        for i in 0..100 {
+            hash_table.insert(format!("key_{}", i), i);
+        }
+
+        let keys: Vec<&String> = hash_table.iter_ordered().map(|(key, _)| key).collect();
+
+        assert_eq!(keys.len(), 102);
+        assert_eq!(keys[..3], ["c", "b", "key_0"]);
+        assert_eq!(keys[101], "key_99");
+        assert_eq!(
+            hash_table.iter_ordered().next(),
+            Some((&"c".to_string(), &10))
+        );
+
+        // an unordered table still lists every key
+        let hash_table: HashTable<String, usize> = (0..10).map(|i| (i.to_string(), i)).collect();
+
+        assert_eq!(hash_table.iter_ordered().count(), 10);
+        assert!(hash_table.order.is_none());
+    }
+
+    #[test]
+    fn test_to_hashmap() {
+        let mut hash_table = HashTable::<String, usize>::new();
+
+        for i in 0..100 {
+            hash_table.insert(i.to_string(), i);
+        }
+
+        hash_table.remove(&"50".to_string());
+
+        let map = hash_table.to_hashmap();
+
+        assert_eq!(map.len(), hash_table.len());
+        assert_eq!(map.get("42"), Some(&42));
+        assert_eq!(map.get("50"), None);
+
+        // and back again
+        let round_trip: HashTable<String, usize> = map.into_iter().collect();
+
+        assert_eq!(round_trip.len(), hash_table.len());
+        assert_eq!(round_trip.get(&"42".to_string()), Some(&42));
+        assert!(hash_table.diff(&round_trip).added.is_empty());
+        assert!(hash_table.diff(&round_trip).removed.is_empty());
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut hash_table = HashTable::<String, usize>::new();
+        assert_eq!(hash_table.capacity(), INITIAL_SIZE);
+
+        for i in 0..60 {
+            hash_table.insert(i.to_string(), i);
+        }
+
+        assert!(hash_table.capacity() > INITIAL_SIZE);
+        assert_eq!(hash_table.len(), 60);
+    }
+
+    #[test]
+    fn test_get_key_value() {
+        // keys which only differ in case are the same key
+        #[derive(Debug, Default, Clone)]
+        struct CaseInsensitive(String);
+
+        impl PartialEq for CaseInsensitive {
+            fn eq(&self, other: &Self) -> bool {
+                self.0.eq_ignore_ascii_case(&other.0)
+            }
+        }
+
+        let mut hash_table =
+            HashTable::<CaseInsensitive, usize>::with_hasher(|key: &CaseInsensitive| {
+                key.0.to_ascii_lowercase().hash()
+            });
+
+        hash_table.insert(CaseInsensitive("Key".to_string()), 1);
+        hash_table.insert(CaseInsensitive("KEY".to_string()), 2);
+
+        let (key, value) = hash_table
+            .get_key_value(&CaseInsensitive("key".to_string()))
+            .unwrap();
+
+        assert_eq!(key.0, "Key");
+        assert_eq!(*value, 2);
+        assert_eq!(hash_table.len(), 1);
+
+        assert_eq!(
+            hash_table.get_key_value(&CaseInsensitive("missing".to_string())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+
+        assert_send::<HashTable<String, usize>>();
+        assert_sync::<HashTable<String, usize>>();
     }
 }